@@ -0,0 +1,156 @@
+use crate::{
+    bot::Bot,
+    utils::{config::BotConfig, position::Position, variant::Variant, *},
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::iter;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("arena")
+        .about("Play bots against each other in-process")
+        .arg(
+            Arg::with_name("black")
+                .long("black")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .required(true)
+                .help("Options of the first bot, e.g. \"--bot-impl minimax\""),
+        )
+        .arg(
+            Arg::with_name("white")
+                .long("white")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .required(true)
+                .help("Options of the second bot"),
+        )
+        .arg(
+            Arg::with_name("games")
+                .long("games")
+                .takes_value(true)
+                .default_value("2")
+                .help("Number of games, colors are swapped every game"),
+        )
+        .arg(
+            Arg::with_name("black_holes")
+                .long("black-holes")
+                .takes_value(true)
+                .help("Black holes for every game, e.g. \"D3 F6\""),
+        )
+}
+
+/// Parses a bot spec with the bot's own command line, the variant is
+/// taken from the arena so both bots play the same game
+pub fn parse_bot_spec(
+    spec: &str,
+    matches: &ArgMatches,
+) -> Result<BotConfig, String> {
+    bot_config(spec, Variant::from_args(matches))
+}
+
+/// Parses a bot spec for a game of `variant`
+pub fn bot_config(spec: &str, variant: Variant) -> Result<BotConfig, String> {
+    let args = iter::once(env!("CARGO_PKG_NAME").to_string())
+        .chain(spec.split_whitespace().map(String::from));
+    let matches = app()
+        .get_matches_from_safe(args)
+        .map_err(|e| format!("Bad bot spec \"{}\": {}", spec, e.message))?;
+    let mut config = BotConfig::from_args(&matches)
+        .map_err(|e| format!("Bad bot spec \"{}\": {}", spec, e))?;
+    config.variant = variant;
+    Ok(config)
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), String> {
+    let specs = [
+        matches.value_of("black").unwrap(),
+        matches.value_of("white").unwrap(),
+    ];
+    let configs = [
+        parse_bot_spec(specs[0], matches)?,
+        parse_bot_spec(specs[1], matches)?,
+    ];
+    let ngames = matches
+        .value_of("games")
+        .unwrap()
+        .parse::<usize>()
+        .map_err(|_| "Bad number of games".to_string())?;
+    let black_holes = match matches.value_of("black_holes") {
+        Some(s) => parse_coords(s)?,
+        None => Vec::new(),
+    };
+    let variant = Variant::from_args(matches);
+
+    // Wins of the first and the second bot, and ties
+    let mut score = [0, 0, 0];
+    let mut position = Position::from_args(matches, &black_holes)?;
+    for game in 0..ngames {
+        // Both games of a pair start from the same opening
+        if game % 2 == 0 && game > 0 {
+            position = Position::from_args(matches, &black_holes)?;
+        }
+        let first = game % 2;
+        let black = create_bot(&configs[first], position, Cell::Black);
+        let white = create_bot(&configs[1 - first], position, Cell::White);
+        let result = play_game([black, white], position, variant);
+        let winner = match result {
+            EndState::BlackWon => first,
+            EndState::WhiteWon => 1 - first,
+            _ => 2,
+        };
+        score[winner] += 1;
+        println!(
+            "game {}: black \"{}\", white \"{}\": {:?}",
+            game + 1,
+            specs[first],
+            specs[1 - first],
+            result
+        );
+    }
+    println!(
+        "\"{}\" won {}, \"{}\" won {}, {} ties",
+        specs[0], score[0], specs[1], score[1], score[2]
+    );
+    Ok(())
+}
+
+/// Plays a game between bots indexed by color, black first
+pub fn play_game(
+    mut bots: [Box<dyn Bot>; 2],
+    mut position: Position,
+    variant: Variant,
+) -> EndState {
+    loop {
+        let allowed_moves = position.allowed_moves();
+        let state = wincheck(
+            &position.board,
+            &allowed_moves,
+            variant,
+            position.to_move,
+        );
+        if state.is_over() {
+            return state;
+        }
+        if allowed_moves.is_empty() {
+            position.play(None).unwrap();
+        } else {
+            let mover = (position.to_move == Cell::White) as usize;
+            let (pl_move, report) = bots[mover].run_ai();
+            info!(Search, "{}", report);
+            info!(
+                Protocol,
+                "{:?} played {}",
+                position.to_move,
+                pl_move.0.to_ab()
+            );
+            position.play(Some(pl_move.0)).unwrap();
+            for bot in bots.iter_mut() {
+                bot.apply_move(&pl_move);
+            }
+        }
+        for bot in bots.iter_mut() {
+            bot.set_color(position.to_move);
+        }
+        logger::flush();
+    }
+}
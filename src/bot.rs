@@ -1,5 +1,7 @@
-use crate::utils::{AllowedMoves, Cell, EndState, LogFile, PlayerMove};
-use std::io::Write;
+use crate::utils::{
+    position::Position, report::SearchReport, AllowedMoves, Cell, EndState,
+    PlayerMove,
+};
 
 pub trait Bot {
     fn allowed_tiles(&self) -> AllowedMoves;
@@ -8,9 +10,11 @@ pub trait Bot {
     fn current_color(&self) -> Cell;
     fn self_color(&self) -> Cell;
     fn set_color(&mut self, color: Cell);
-    fn run_ai(&self) -> PlayerMove;
-    fn get_logfile(&self) -> LogFile;
+    /// Starts over from `position` playing `my_color`, keeping the settings
+    fn new_game(&mut self, position: Position, my_color: Cell);
+    fn run_ai(&self) -> (PlayerMove, SearchReport);
+    #[allow(dead_code)]
     fn report(&mut self) {
-        log!(self, "Game result: {:?}", self.status());
+        info!(Search, "Game result: {:?}", self.status());
     }
 }
@@ -0,0 +1,63 @@
+//! Reversi and anti reversi engines: minimax and several flavours of MCTS
+//! over boards from 4x4 to 10x10 with optional black holes.
+//!
+//! The items re-exported here are the stable API. The remaining public
+//! modules back the `reversi_bot` binary and may change at any time.
+
+#[macro_use]
+#[doc(hidden)]
+pub mod utils;
+#[doc(hidden)]
+pub mod arena;
+#[doc(hidden)]
+pub mod bot;
+mod mcts;
+mod mcts2;
+mod mcts3;
+mod mcts4;
+mod minimax;
+#[doc(hidden)]
+pub mod nboard;
+#[doc(hidden)]
+pub mod perft;
+#[doc(hidden)]
+pub mod play;
+#[doc(hidden)]
+pub mod runner;
+pub mod search;
+#[doc(hidden)]
+pub mod session;
+#[doc(hidden)]
+pub mod tournament;
+
+pub use crate::{
+    bot::Bot,
+    perft::perft,
+    search::Engine,
+    utils::{
+        board::Board,
+        config::BotConfig,
+        game::{Game, Reversi},
+        limits::Limits,
+        point::Point,
+        position::Position,
+        report::{RootMoveStats, SearchReport},
+        variant::Variant,
+        wincheck, AllowedMoves, Cell, EndState, PlayerMove, Score, Variation,
+    },
+};
+
+/// Settings a `BotConfig` is made of
+pub mod config {
+    pub use crate::utils::{
+        final_move::FinalMove,
+        level::Level,
+        rollout::Policy,
+        tree2::{Minimax, Priors},
+    };
+}
+
+/// Static evaluations, positive when `color` is ahead in regular reversi
+pub mod eval {
+    pub use crate::utils::sev::{sev3, static_eval, tile_weight};
+}
@@ -1,15 +1,10 @@
-#[macro_use]
-mod utils;
-mod bot;
-mod mcts;
-mod mcts2;
-mod mcts3;
-mod minimax;
-mod runner;
-
-use runner::Runner;
-use std::{thread, time};
-use utils::{parse_args, select_bot_impl};
+use reversi_bot::{
+    arena, nboard, perft, play,
+    runner::Runner,
+    session, tournament,
+    utils::{logger::Logger, parse_args, rng, select_bot_impl},
+};
+use std::{process, thread, time};
 
 fn main() {
     let matches = parse_args();
@@ -20,6 +15,25 @@ fn main() {
         return;
     }
 
+    Logger::from_args(&matches).init();
+    rng::seed_from_args(&matches);
+    let result = match matches.subcommand() {
+        ("arena", Some(sub_matches)) => Some(arena::run(sub_matches)),
+        ("nboard", Some(sub_matches)) => Some(nboard::run(sub_matches)),
+        ("perft", Some(sub_matches)) => Some(perft::run(sub_matches)),
+        ("play", Some(sub_matches)) => Some(play::run(sub_matches)),
+        ("session", Some(sub_matches)) => Some(session::run(sub_matches)),
+        ("tournament", Some(sub_matches)) => Some(tournament::run(sub_matches)),
+        _ => None,
+    };
+    if let Some(result) = result {
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
     let bot = select_bot_impl(&matches);
     let mut runner = Runner::new(bot);
     runner.run();
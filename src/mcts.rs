@@ -1,154 +1,159 @@
 use crate::{
     bot::Bot,
-    utils::{board::Board, *},
+    utils::{
+        board::Board,
+        config::BotConfig,
+        final_move::{self, FinalMove, RootSearch},
+        level::Level,
+        limits::{Limits, Timer},
+        position::Position,
+        report::{RootMoveStats, SearchReport},
+        rollout::{Policy, Rollout},
+        variant::{self, Variant},
+        *,
+    },
 };
-use crossbeam::channel::{select, unbounded};
-use rand::thread_rng;
 use rayon::prelude::*;
-use std::{
-    collections::HashMap,
-    io::Write,
-    thread,
-    time::{Duration, Instant},
-};
+use std::time::Instant;
 
 pub struct MCTSBot {
     board: Board,
-    log_file: LogFile,
-    move_maxtime: Duration,
+    limits: Limits,
     my_color: Cell,
-    win_state: EndState,
     current_color: Cell,
-    is_anti: bool,
+    variant: Variant,
+    level: Option<Level>,
+    final_move: FinalMove,
+    rollout: Rollout,
 }
 
 impl MCTSBot {
-    pub fn new(arg_matches: &clap::ArgMatches) -> Self {
-        let black_hole = read_black_hole(arg_matches);
-        let my_color = Chan::read().color();
-
-        let is_anti = !arg_matches.is_present("no_anti");
+    pub fn new(config: &BotConfig, position: Position, my_color: Cell) -> Self {
+        let variant = config.variant;
 
-        let board = Board::initial(black_hole);
-        let current_color = Cell::Black;
+        let board = position.board;
+        let current_color = position.to_move;
 
-        let move_maxtime = arg_matches
-            .value_of("time_limit")
-            .map(|it| it.parse::<u64>().unwrap())
-            .unwrap();
+        let level = config.level;
+        let limits = config.limits;
+        let final_move = config.final_move;
+        let rollout = config.rollout(Policy::Random);
 
         let bot = Self {
             board,
             my_color,
             current_color,
-            win_state: EndState::Unknown,
 
-            log_file: get_logfile(&arg_matches),
-            is_anti,
-            move_maxtime: Duration::from_millis(move_maxtime),
+            variant,
+            level,
+            limits,
+            final_move,
+            rollout,
         };
 
-        log!(bot, "alg: Basic MCTS");
-        log!(bot, "black hole: {:?}", black_hole.map(|p| p.to_ab()));
-        log!(bot, "my color: {:?}", my_color);
-        log!(bot, "anti reversi mode: {}", is_anti);
-        log!(bot, "move timeout: {}\n\nBEGIN:", move_maxtime);
+        info!(Search, "alg: Basic MCTS");
+        info!(Search, "variant: {:?}", variant);
+        info!(Time, "move timeout: {:?}", limits.time);
+        info!(Search, "playouts per move: {:?}", limits.playouts);
+        info!(Search, "final move: {:?}", final_move);
+        info!(Search, "rollout: {:?}", rollout);
+        if let Some(level) = level {
+            info!(Search, "level: {:?}", level);
+        }
 
         bot
     }
 
-    fn mcts(&self) -> PlayerMove {
+    fn mcts(&self) -> (PlayerMove, SearchReport) {
         let now = Instant::now();
         let allowed_moves = self.board.allowed_moves(self.current_color);
         if allowed_moves.len() == 1 {
-            return allowed_moves.first().unwrap().clone();
+            let pl_move = allowed_moves.first().unwrap().clone();
+            let report = SearchReport::forced(self.my_color, pl_move.0);
+            return (pl_move, report);
         }
-        let (results_tx, results_rx) = unbounded::<(EndState, usize)>();
-        let (stop_tx, stop_rx) = unbounded::<()>();
 
-        let results_handle = thread::spawn({
-            let maxtime = self.move_maxtime;
-            let my_color = self.my_color;
-            let mut results: HashMap<usize, (u64, u64)> = HashMap::new();
-
-            move || {
-                let t = Instant::now();
-                while t.elapsed() < maxtime {
-                    if let Ok((end_state, move_index)) = results_rx.recv() {
-                        let tup = results.entry(move_index).or_insert((0, 0));
-                        if end_state.won(my_color) {
-                            *tup = (tup.0 + 1, tup.1 + 1);
-                        } else {
-                            *tup = (tup.0, tup.1 + 1);
-                        }
-                    }
-                }
-                stop_tx.send(()).unwrap();
-                results
-            }
-        });
+        let (chosen, roots) = self.final_move.search(
+            allowed_moves.len(),
+            self.limits,
+            |indices, limits| self.search(&allowed_moves, indices, limits),
+        );
+        let mut report = final_move::report(self.my_color, chosen, roots);
+        report.elapsed = now.elapsed();
+        (allowed_moves[chosen].clone(), report)
+    }
 
-        allowed_moves.par_iter().enumerate().for_each(
-            move |(index, pl_move)| {
-                let rng = thread_rng();
-                loop {
-                    let sim_result = Board::sim(
-                        &self.board,
-                        pl_move.clone(),
-                        self.my_color,
-                        self.is_anti,
-                        rng,
+    /// Plays out the root moves at `indices` within `limits`
+    fn search(
+        &self,
+        allowed_moves: &AllowedMoves,
+        indices: &[usize],
+        limits: Limits,
+    ) -> Vec<RootSearch> {
+        let timer = Timer::start(limits.time, indices.len());
+
+        let roots = indices
+            .par_iter()
+            .enumerate()
+            .map(|(n, &index)| {
+                let pl_move = &allowed_moves[index];
+                let mut rng = rng::thread_rng();
+                let playouts = limits.playouts_for(n, indices.len());
+                let board = self.board.with_move(pl_move, self.my_color);
+                let mut played = Vec::new();
+                let (mut wins, mut reward, mut total) = (0, 0.0, 0);
+                while total < playouts && !timer.is_over() {
+                    played.clear();
+                    let outcome = self.rollout.play(
+                        board,
+                        self.my_color.opposite(),
+                        self.variant,
+                        &mut rng,
+                        &mut played,
                     );
-                    select! {
-                        recv(stop_rx) -> _ => break,
-                        send(results_tx, (sim_result, index)) -> _ => {},
+                    if outcome.result.won(self.my_color) {
+                        wins += 1;
                     }
+                    reward +=
+                        variant::result_value(outcome.result, self.my_color);
+                    total += 1;
                 }
-            },
-        );
-
-        let results = results_handle.join().unwrap();
-        log!(
-            self,
-            "total plays: {}; allowed moves: {}",
-            results.values().map(|t| t.1).sum::<u64>(),
-            allowed_moves.len(),
-        );
-
-        // log!(
-        //     self,
-        //     "{}",
-        //     results
-        //         .values()
-        //         .map(|(wins, total)| format!("{}/{}", wins, total))
-        //         .collect::<Vec<String>>()
-        //         .join(", ")
-        // );
-
-        let mut best_move = &allowed_moves[0];
-        let mut max_ratio = 0f64;
-        for (index, (wins, total)) in results.iter() {
-            let ratio = *wins as f64 / *total as f64;
-            if ratio > max_ratio {
-                best_move = &allowed_moves[*index];
-                max_ratio = ratio;
-            }
-        }
-        log!(self, "time: {}ms", now.elapsed().as_millis(),);
+                let ratio = reward / total as f64;
+                RootSearch {
+                    stats: RootMoveStats {
+                        point: pl_move.0,
+                        visits: total,
+                        wins,
+                        score: ratio,
+                    },
+                    // Flat search can't see further than its own move
+                    pv: Variation(vec![(pl_move.0, ratio)]),
+                    depth: 1,
+                    proven_win: false,
+                }
+            })
+            .collect::<Vec<_>>();
 
-        best_move.clone()
+        timer.finish();
+        roots
     }
 }
 
 impl Bot for MCTSBot {
     fn status(&self) -> EndState {
-        self.win_state
+        let allowed_moves = self.allowed_tiles();
+        wincheck(
+            &self.board,
+            &allowed_moves,
+            self.variant,
+            self.current_color,
+        )
     }
     fn allowed_tiles(&self) -> AllowedMoves {
         self.board.allowed_moves(self.current_color)
     }
     fn apply_move(&mut self, player_move: &PlayerMove) {
-        self.board.apply_move(&player_move, self.current_color);
+        self.board.apply_move(player_move, self.current_color);
     }
     fn current_color(&self) -> Cell {
         self.current_color
@@ -159,10 +164,16 @@ impl Bot for MCTSBot {
     fn self_color(&self) -> Cell {
         self.my_color
     }
-    fn run_ai(&self) -> PlayerMove {
-        self.mcts()
+    fn new_game(&mut self, position: Position, my_color: Cell) {
+        self.board = position.board;
+        self.current_color = position.to_move;
+        self.my_color = my_color;
     }
-    fn get_logfile(&self) -> LogFile {
-        self.log_file.clone()
+    fn run_ai(&self) -> (PlayerMove, SearchReport) {
+        let result = self.mcts();
+        match self.level {
+            Some(level) => level.apply(result, &self.allowed_tiles()),
+            None => result,
+        }
     }
 }
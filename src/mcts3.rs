@@ -1,164 +1,231 @@
 use crate::{
     bot::Bot,
     utils::*,
-    utils::{board::Board, tree2::Node},
+    utils::{
+        board::Board,
+        config::BotConfig,
+        final_move::{self, FinalMove, RootSearch},
+        game::{Game, Reversi},
+        level::Level,
+        limits::{Limits, Timer},
+        position::Position,
+        report::{RootMoveStats, SearchReport},
+        rollout::{Policy, Rollout},
+        tree::TreeNode,
+        tree2::{Minimax, Node, Priors},
+        variant::Variant,
+    },
 };
-use crossbeam::channel;
 use rayon::prelude::*;
-use std::{
-    io::Write,
-    thread,
-    time::{Duration, Instant},
-};
+use std::time::Instant;
 
 pub struct MCTSMinimaxBot {
     board: Board,
-    log_file: LogFile,
-    move_maxtime: Duration,
+    limits: Limits,
     my_color: Cell,
-    win_state: EndState,
     current_color: Cell,
-    is_anti: bool,
+    variant: Variant,
+    level: Option<Level>,
+    final_move: FinalMove,
+    rollout: Rollout,
     exploitation_value: f64,
-    minimax_threshold: i32,
+    priors: Priors,
+    margin_weight: f64,
+    minimax: Minimax,
 }
 
 impl MCTSMinimaxBot {
-    pub fn new(arg_matches: &clap::ArgMatches) -> Self {
-        let black_hole = read_black_hole(arg_matches);
-        let my_color = Chan::read().color();
-
-        let is_anti = !arg_matches.is_present("no_anti");
+    pub fn new(config: &BotConfig, position: Position, my_color: Cell) -> Self {
+        let variant = config.variant;
 
-        let board = Board::initial(black_hole);
-        let current_color = Cell::Black;
+        let board = position.board;
+        let current_color = position.to_move;
 
-        let move_maxtime = arg_matches
-            .value_of("time_limit")
-            .map(|it| it.parse::<u64>().unwrap())
-            .unwrap();
+        let level = config.level;
+        let limits = config.limits;
+        let final_move = config.final_move;
+        let rollout = config.rollout(Policy::EpsilonGreedy(0.0));
 
-        let exploitation_value = arg_matches
-            .value_of("exploitation_value")
-            .map(str::parse::<f64>)
-            .map(Result::unwrap)
-            .unwrap_or(2f64.sqrt());
+        let exploitation_value =
+            config.exploitation_value.unwrap_or(2f64.sqrt());
+        let priors = config.priors;
+        let minimax = config.minimax;
+        let margin_weight = config.margin_weight;
 
         let bot = Self {
             board,
             my_color,
             current_color,
-            win_state: EndState::Unknown,
-            log_file: get_logfile(&arg_matches),
-            is_anti,
-            move_maxtime: Duration::from_millis(move_maxtime),
+            variant,
+            level,
+            limits,
+            final_move,
+            rollout,
             exploitation_value,
-            minimax_threshold: 5,
+            priors,
+            margin_weight,
+            minimax,
         };
 
-        log!(bot, "alg: Advanced MCTS");
-        log!(bot, "black hole: {:?}", black_hole.map(|p| p.to_ab()));
-        log!(bot, "my color: {:?}", my_color);
-        log!(bot, "anti reversi mode: {}", is_anti);
-        log!(bot, "move timeout: {}\n\nBEGIN:", move_maxtime);
+        info!(Search, "alg: Advanced MCTS");
+        info!(Search, "variant: {:?}", variant);
+        info!(Search, "priors: {:?}", priors);
+        info!(Search, "margin weight: {}", margin_weight);
+        info!(Search, "minimax: {:?}", minimax);
+        info!(Time, "move timeout: {:?}", limits.time);
+        info!(Search, "playouts per move: {:?}", limits.playouts);
+        info!(Search, "final move: {:?}", final_move);
+        info!(Search, "rollout: {:?}", rollout);
+        if let Some(level) = level {
+            info!(Search, "level: {:?}", level);
+        }
 
         bot
     }
 
-    fn mcts(&self) -> PlayerMove {
+    fn mcts(&self) -> (PlayerMove, SearchReport) {
+        let now = Instant::now();
         let allowed_moves = self.board.allowed_moves(self.current_color);
 
         if allowed_moves.len() == 1 {
-            return allowed_moves.first().unwrap().clone();
+            let pl_move = allowed_moves.first().unwrap().clone();
+            let report = SearchReport::forced(self.my_color, pl_move.0);
+            return (pl_move, report);
         }
 
-        let (stop_tx, stop_rx) = channel::unbounded::<()>();
-        let tim_thread = thread::spawn({
-            let stop_signals_count = allowed_moves.len();
-            let max_time = self.move_maxtime;
-            move || {
-                thread::sleep(max_time);
-                for _ in 0..stop_signals_count {
-                    stop_tx.send(()).ok();
-                }
-            }
-        });
+        let (chosen, roots) = self.final_move.search(
+            allowed_moves.len(),
+            self.limits,
+            |indices, limits| self.search(&allowed_moves, indices, limits),
+        );
+        let mut report = final_move::report(self.my_color, chosen, roots);
+        report.elapsed = now.elapsed();
+        (allowed_moves[chosen].clone(), report)
+    }
+
+    /// Searches the root moves at `indices` within `limits`
+    fn search(
+        &self,
+        allowed_moves: &AllowedMoves,
+        indices: &[usize],
+        limits: Limits,
+    ) -> Vec<RootSearch> {
+        let timer = Timer::start(limits.time, indices.len());
 
-        let scores = allowed_moves
+        let roots = indices
             .par_iter()
-            .map(|pl_move| {
-                let new_board = self.board.with_move(pl_move, self.my_color);
+            .enumerate()
+            .map(|(n, &index)| {
+                let pl_move = &allowed_moves[index];
+                let mut game = Reversi {
+                    position: Position {
+                        board: self.board,
+                        to_move: self.my_color,
+                    },
+                    variant: self.variant,
+                };
+                game.play(&Some(pl_move.clone()));
 
                 let tree = Node::new(
-                    new_board,
                     self.my_color.opposite(),
-                    Some(pl_move.clone()),
+                    Some(Some(pl_move.clone())),
                 );
+                let mut undos = Vec::new();
+                let playouts = limits.playouts_for(n, indices.len());
+
+                for _ in 0..playouts {
+                    if timer.is_over() || tree.borrow().proof.is_some() {
+                        break;
+                    }
+                    let selected = Node::selection(
+                        tree.clone(),
+                        &mut game,
+                        &mut undos,
+                        self.exploitation_value,
+                        self.priors,
+                        self.minimax,
+                    );
 
-                while stop_rx.try_recv().is_err() {
-                    let selected =
-                        Node::selection(tree.clone(), self.exploitation_value);
-
-                    let expanded = Node::expansion(selected, self.is_anti);
+                    let expanded = Node::expansion(
+                        selected,
+                        &mut game,
+                        &mut undos,
+                        self.priors,
+                        self.minimax,
+                    );
 
-                    let rollout_result = Node::simulate(
+                    let outcome = Node::simulate(
                         expanded.clone(),
-                        self.is_anti,
-                        false,
-                        self.my_color,
+                        &game.position.board,
+                        self.variant,
+                        &self.rollout,
                     );
 
                     Node::back_propagate(
-                        expanded,
-                        rollout_result,
-                        self.is_anti,
+                        expanded.clone(),
+                        outcome.result,
+                        |color| {
+                            outcome.reward(
+                                color,
+                                self.variant,
+                                self.margin_weight,
+                            )
+                        },
                     );
+                    if self.minimax.is_on() {
+                        Node::back_up_minimax(expanded);
+                    }
+                    while let Some(undo) = undos.pop() {
+                        game.undo(undo);
+                    }
                 }
 
+                // The line stops at a pass, it has no point to show
+                let pv = Node::principal_variation(&tree)
+                    .into_iter()
+                    .map_while(|(m, score)| m.map(|m| (m.0, score)))
+                    .collect();
+                let depth = Node::depth(&tree);
                 let node = tree.borrow();
-
-                (
-                    (node.nwins, node.nvisits),
-                    node.player_move.clone().unwrap(),
-                )
+                let stats = RootMoveStats {
+                    point: pl_move.0,
+                    visits: node.nvisits,
+                    wins: node.nwins,
+                    score: node
+                        .proven_score(self.my_color)
+                        .unwrap_or(node.mixed_score(self.minimax.weight)),
+                };
+                let proven_win =
+                    node.proof.is_some_and(|r| r.won(self.my_color));
+                RootSearch {
+                    stats,
+                    pv: Variation(pv),
+                    depth,
+                    proven_win,
+                }
             })
             .collect::<Vec<_>>();
 
-        tim_thread.join().unwrap();
-
-        log!(
-            self,
-            "final scores: [{}]",
-            scores
-                .iter()
-                .map(|((w, v), _)| format!("{}/{}", w, v))
-                .collect::<Vec<_>>()
-                .join(", ")
-        );
-
-        let mut max_score = f64::MIN;
-        let mut best_move = &scores[0].1;
-        for ((w, v), player_move) in scores.iter() {
-            // let score = *v as f64;
-            let score = *w as f64 / *v as f64;
-            if score > max_score {
-                max_score = score;
-                best_move = &player_move
-            }
-        }
-        best_move.clone()
+        timer.finish();
+        roots
     }
 }
 
 impl Bot for MCTSMinimaxBot {
     fn status(&self) -> EndState {
-        self.win_state
+        let allowed_moves = self.allowed_tiles();
+        wincheck(
+            &self.board,
+            &allowed_moves,
+            self.variant,
+            self.current_color,
+        )
     }
     fn allowed_tiles(&self) -> AllowedMoves {
         self.board.allowed_moves(self.current_color)
     }
     fn apply_move(&mut self, player_move: &PlayerMove) {
-        self.board.apply_move(&player_move, self.current_color);
+        self.board.apply_move(player_move, self.current_color);
     }
     fn current_color(&self) -> Cell {
         self.current_color
@@ -169,10 +236,16 @@ impl Bot for MCTSMinimaxBot {
     fn self_color(&self) -> Cell {
         self.my_color
     }
-    fn run_ai(&self) -> PlayerMove {
-        self.mcts()
+    fn new_game(&mut self, position: Position, my_color: Cell) {
+        self.board = position.board;
+        self.current_color = position.to_move;
+        self.my_color = my_color;
     }
-    fn get_logfile(&self) -> LogFile {
-        self.log_file.clone()
+    fn run_ai(&self) -> (PlayerMove, SearchReport) {
+        let result = self.mcts();
+        match self.level {
+            Some(level) => level.apply(result, &self.allowed_tiles()),
+            None => result,
+        }
     }
 }
@@ -0,0 +1,250 @@
+use crate::{
+    bot::Bot,
+    utils::*,
+    utils::{
+        board::Board,
+        config::BotConfig,
+        final_move::{self, FinalMove, RootSearch},
+        game::{Game, Reversi},
+        level::Level,
+        limits::{Limits, Timer},
+        position::Position,
+        report::{RootMoveStats, SearchReport},
+        rollout::{Policy, Rollout},
+        tree::TreeNode,
+        tree2::{Minimax, Node, Priors},
+        variant::Variant,
+    },
+};
+use rayon::prelude::*;
+use std::time::Instant;
+
+pub struct MCTSRaveBot {
+    board: Board,
+    limits: Limits,
+    my_color: Cell,
+    current_color: Cell,
+    variant: Variant,
+    level: Option<Level>,
+    final_move: FinalMove,
+    rollout: Rollout,
+    exploitation_value: f64,
+    priors: Priors,
+    margin_weight: f64,
+    rave_k: f64,
+}
+
+impl MCTSRaveBot {
+    pub fn new(config: &BotConfig, position: Position, my_color: Cell) -> Self {
+        let variant = config.variant;
+
+        let board = position.board;
+        let current_color = position.to_move;
+
+        let level = config.level;
+        let limits = config.limits;
+        let final_move = config.final_move;
+        let rollout = config.rollout(Policy::Random);
+
+        let exploitation_value =
+            config.exploitation_value.unwrap_or(2f64.sqrt());
+        let priors = config.priors;
+        let margin_weight = config.margin_weight;
+        let rave_k = config.rave_k;
+
+        let bot = Self {
+            board,
+            my_color,
+            current_color,
+            variant,
+            level,
+            limits,
+            final_move,
+            rollout,
+            exploitation_value,
+            priors,
+            margin_weight,
+            rave_k,
+        };
+
+        info!(Search, "alg: MCTS with RAVE, k = {}", rave_k);
+        info!(Search, "variant: {:?}", variant);
+        info!(Search, "priors: {:?}", priors);
+        info!(Search, "margin weight: {}", margin_weight);
+        info!(Time, "move timeout: {:?}", limits.time);
+        info!(Search, "playouts per move: {:?}", limits.playouts);
+        info!(Search, "final move: {:?}", final_move);
+        info!(Search, "rollout: {:?}", rollout);
+        if let Some(level) = level {
+            info!(Search, "level: {:?}", level);
+        }
+
+        bot
+    }
+
+    fn mcts(&self) -> (PlayerMove, SearchReport) {
+        let now = Instant::now();
+        let allowed_moves = self.board.allowed_moves(self.current_color);
+
+        if allowed_moves.len() == 1 {
+            let pl_move = allowed_moves.first().unwrap().clone();
+            let report = SearchReport::forced(self.my_color, pl_move.0);
+            return (pl_move, report);
+        }
+
+        let (chosen, roots) = self.final_move.search(
+            allowed_moves.len(),
+            self.limits,
+            |indices, limits| self.search(&allowed_moves, indices, limits),
+        );
+        let mut report = final_move::report(self.my_color, chosen, roots);
+        report.elapsed = now.elapsed();
+        (allowed_moves[chosen].clone(), report)
+    }
+
+    /// Searches the root moves at `indices` within `limits`
+    fn search(
+        &self,
+        allowed_moves: &AllowedMoves,
+        indices: &[usize],
+        limits: Limits,
+    ) -> Vec<RootSearch> {
+        let timer = Timer::start(limits.time, indices.len());
+
+        let roots = indices
+            .par_iter()
+            .enumerate()
+            .map(|(n, &index)| {
+                let pl_move = &allowed_moves[index];
+                let mut game = Reversi {
+                    position: Position {
+                        board: self.board,
+                        to_move: self.my_color,
+                    },
+                    variant: self.variant,
+                };
+                game.play(&Some(pl_move.clone()));
+
+                let tree = Node::new(
+                    self.my_color.opposite(),
+                    Some(Some(pl_move.clone())),
+                );
+                let mut undos = Vec::new();
+                let mut played = Vec::new();
+                let mut rng = rng::thread_rng();
+                let playouts = limits.playouts_for(n, indices.len());
+
+                for _ in 0..playouts {
+                    if timer.is_over() || tree.borrow().proof.is_some() {
+                        break;
+                    }
+                    let selected = Node::selection_rave(
+                        tree.clone(),
+                        &mut game,
+                        &mut undos,
+                        self.exploitation_value,
+                        self.rave_k,
+                        self.priors,
+                    );
+                    let expanded = Node::expansion(
+                        selected,
+                        &mut game,
+                        &mut undos,
+                        self.priors,
+                        Minimax::default(),
+                    );
+
+                    played.clear();
+                    let outcome = self.rollout.play(
+                        game.position.board,
+                        game.to_move(),
+                        self.variant,
+                        &mut rng,
+                        &mut played,
+                    );
+                    Node::back_propagate_rave(
+                        expanded,
+                        outcome.result,
+                        |color| {
+                            outcome.reward(
+                                color,
+                                self.variant,
+                                self.margin_weight,
+                            )
+                        },
+                        played.iter().map(|&(p, color)| (p.usize(), color)),
+                    );
+                    while let Some(undo) = undos.pop() {
+                        game.undo(undo);
+                    }
+                }
+
+                // The line stops at a pass, it has no point to show
+                let pv = Node::principal_variation(&tree)
+                    .into_iter()
+                    .map_while(|(m, score)| m.map(|m| (m.0, score)))
+                    .collect();
+                let depth = Node::depth(&tree);
+                let node = tree.borrow();
+                let stats = RootMoveStats {
+                    point: pl_move.0,
+                    visits: node.nvisits,
+                    wins: node.nwins,
+                    score: node
+                        .proven_score(self.my_color)
+                        .unwrap_or(node.reward / node.nvisits as f64),
+                };
+                let proven_win =
+                    node.proof.is_some_and(|r| r.won(self.my_color));
+                RootSearch {
+                    stats,
+                    pv: Variation(pv),
+                    depth,
+                    proven_win,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        timer.finish();
+        roots
+    }
+}
+
+impl Bot for MCTSRaveBot {
+    fn status(&self) -> EndState {
+        let allowed_moves = self.allowed_tiles();
+        wincheck(
+            &self.board,
+            &allowed_moves,
+            self.variant,
+            self.current_color,
+        )
+    }
+    fn allowed_tiles(&self) -> AllowedMoves {
+        self.board.allowed_moves(self.current_color)
+    }
+    fn apply_move(&mut self, player_move: &PlayerMove) {
+        self.board.apply_move(player_move, self.current_color);
+    }
+    fn current_color(&self) -> Cell {
+        self.current_color
+    }
+    fn set_color(&mut self, color: Cell) {
+        self.current_color = color;
+    }
+    fn self_color(&self) -> Cell {
+        self.my_color
+    }
+    fn new_game(&mut self, position: Position, my_color: Cell) {
+        self.board = position.board;
+        self.current_color = position.to_move;
+        self.my_color = my_color;
+    }
+    fn run_ai(&self) -> (PlayerMove, SearchReport) {
+        let result = self.mcts();
+        match self.level {
+            Some(level) => level.apply(result, &self.allowed_tiles()),
+            None => result,
+        }
+    }
+}
@@ -1,52 +1,64 @@
 use crate::{
     bot::Bot,
     utils::sev::*,
-    utils::{board::Board, *},
+    utils::{
+        board::Board,
+        config::BotConfig,
+        game::{Game, Reversi},
+        level::Level,
+        limits::Limits,
+        negamax::Negamax,
+        position::Position,
+        report::{RootMoveStats, SearchReport},
+        variant::Variant,
+        *,
+    },
 };
-use clap::ArgMatches;
+use rand::Rng;
 use rayon::prelude::*;
-use std::{io::Write, sync::Mutex};
+use std::{sync::Mutex, time::Instant};
 
 pub struct MinimaxBot {
     board: Board,
     my_color: Cell,
     current_color: Cell,
-    win_state: EndState,
     max_tree_depth: usize,
-    log_file: LogFile,
-    is_anti: bool,
+    limits: Limits,
+    variant: Variant,
+    level: Option<Level>,
 }
 
 impl MinimaxBot {
-    pub fn new(arg_matches: &ArgMatches) -> Self {
-        let black_hole = read_black_hole(arg_matches);
-        let my_color = Chan::read().color();
+    pub fn new(config: &BotConfig, position: Position, my_color: Cell) -> Self {
+        let variant = config.variant;
 
-        let is_anti = !arg_matches.is_present("no_anti");
-
-        let board = Board::initial(black_hole);
-        let current_color = Cell::Black;
-        let _allowed_moves = board.allowed_moves(current_color);
-        let max_tree_depth = arg_matches
-            .value_of("max_depth")
-            .map(|s| s.parse::<usize>().unwrap())
-            .unwrap();
+        let board = position.board;
+        let current_color = position.to_move;
+        let level = config.level;
+        let max_tree_depth = match level {
+            Some(level) => level.max_depth,
+            None => config.max_depth,
+        };
+        let limits = config.limits;
 
         let bot = Self {
             board,
             my_color,
             current_color,
-            win_state: EndState::Unknown,
             max_tree_depth,
-            log_file: get_logfile(&arg_matches),
-            is_anti,
+            limits,
+            variant,
+            level,
         };
 
-        log!(bot, "alg: MiniMax");
-        log!(bot, "black hole: {:?}", black_hole.map(|p| p.to_ab()));
-        log!(bot, "my color: {:?}", my_color);
-        log!(bot, "anti reversi mode: {}", is_anti);
-        log!(bot, "tree depth: {}\n\nBEGIN:", max_tree_depth);
+        info!(Search, "alg: MiniMax");
+        info!(Search, "variant: {:?}", variant);
+        info!(Search, "tree depth: {}", max_tree_depth);
+        info!(Time, "move timeout: {:?}", limits.time);
+        info!(Search, "nodes per move: {:?}", limits.playouts);
+        if let Some(level) = level {
+            info!(Search, "level: {:?}", level);
+        }
 
         bot
     }
@@ -70,7 +82,7 @@ impl MinimaxBot {
 
         for pl_move in allowed_moves.iter() {
             let next_board = self.board.with_move(pl_move, self.my_color);
-            let eval = self.minimax(
+            let _eval = self.minimax(
                 next_board,
                 self.max_tree_depth - 1,
                 alphabeta,
@@ -110,8 +122,9 @@ impl MinimaxBot {
 
         if depth == 0 || allowed_moves.is_empty() {
             let mul = if self.my_color == color { 1 } else { -1 };
-            let is_even = self.max_tree_depth % 2 == 0;
-            return sev3(&board, self.my_color, is_even) * mul;
+            let is_even = self.max_tree_depth.is_multiple_of(2);
+            let score = sev3(&board, self.my_color, is_even);
+            return score * self.variant.sign() * mul;
         }
 
         let (mut alpha, beta) = ab;
@@ -125,7 +138,7 @@ impl MinimaxBot {
                 color.opposite(),
             );
 
-            best_eval = max_of(best_eval, -1 * eval);
+            best_eval = max_of(best_eval, -eval);
             alpha = max_of(alpha, best_eval);
             if alpha >= beta {
                 break;
@@ -134,80 +147,152 @@ impl MinimaxBot {
         best_eval
     }
 
-    pub fn run_negamax(&self) -> PlayerMove {
+    /// Searches one ply deeper at a time until the limits stop a search,
+    /// the deepest finished search decides
+    pub fn run_negamax(&self) -> (PlayerMove, SearchReport) {
+        let now = Instant::now();
         let allowed_moves = self.board.allowed_moves(self.current_color);
-        let first = allowed_moves.first();
         if allowed_moves.len() == 1 {
-            return first.unwrap().clone();
+            let pl_move = allowed_moves.first().unwrap().clone();
+            let report = SearchReport::forced(self.my_color, pl_move.0);
+            return (pl_move, report);
         }
 
-        let mut best_move = (Score::MIN, first.unwrap());
-        let (mut alpha, beta) = (Score::MIN, Score::MAX);
-        let mul = if self.is_anti { 1 } else { -1 };
-
-        for pl_move in allowed_moves.iter() {
-            let new_board = self.board.with_move(&pl_move, self.my_color);
-            let score = self.negamax(
-                new_board,
-                self.max_tree_depth,
-                alpha,
-                beta,
-                !self.my_color,
-            ) * mul;
-            if score > best_move.0 {
-                best_move = (score, &pl_move);
+        let deadline = self.limits.time.map(|time| now + time);
+        let mut nodes = 0;
+        let mut best = None;
+        // Each iteration searches the last best move first to cut more
+        let mut order = (0..allowed_moves.len()).collect::<Vec<_>>();
+        // Without limits only the deepest search counts
+        let first = match self.limits.check() {
+            Ok(()) => 0,
+            Err(_) => self.max_tree_depth,
+        };
+        for depth in first..=self.max_tree_depth {
+            let mut negamax =
+                Negamax::new(|game: &Reversi| self.evaluate(game, depth));
+            // The shallowest search always finishes to have a move
+            if best.is_some() {
+                negamax.deadline = deadline;
+                negamax.max_nodes =
+                    self.limits.playouts.map(|max| max.saturating_sub(nodes));
             }
-
-            alpha = max_of(alpha, best_move.0);
-            if alpha >= beta {
-                break;
+            let result =
+                self.search_depth(&allowed_moves, &order, depth, &mut negamax);
+            nodes += negamax.nodes;
+            match result {
+                Some(result) => {
+                    order.retain(|&index| index != result.0);
+                    order.insert(0, result.0);
+                    best = Some(result);
+                }
+                None => break,
             }
         }
-        best_move.1.clone()
+
+        let (index, mut report) = best.unwrap();
+        report.nodes = nodes;
+        report.elapsed = now.elapsed();
+        (allowed_moves[index].clone(), report)
     }
 
-    #[inline]
-    pub fn negamax(
+    /// Index of the best root move searched `depth` plies deeper with its
+    /// report, `None` when the search gave up. Root moves are tried by
+    /// their indices in `order`
+    fn search_depth<F: FnMut(&Reversi) -> Score>(
         &self,
-        board: Board,
+        allowed_moves: &AllowedMoves,
+        order: &[usize],
         depth: usize,
-        mut alpha: i32,
-        beta: i32,
-        color: Cell,
-    ) -> Score {
-        let allowed_moves = board.allowed_moves(color);
-        if depth == 0 || allowed_moves.is_empty() {
-            let mul = if color == self.my_color { 1 } else { -1 };
-            let even_depth = self.max_tree_depth % 2 == 0;
-            return sev3(&board, self.my_color, even_depth) * mul;
-        }
+        negamax: &mut Negamax<F>,
+    ) -> Option<(usize, SearchReport)> {
+        let mut report = SearchReport::new(self.my_color);
+        let mut best_move = (Score::MIN, 0, Vec::new());
+        let (mut alpha, beta) = (-Score::MAX, Score::MAX);
+        let mut game = Reversi {
+            position: Position {
+                board: self.board,
+                to_move: self.my_color,
+            },
+            variant: self.variant,
+        };
 
-        let mut best = Score::MIN;
-        for pl_move in allowed_moves.iter() {
-            let new_board = board.with_move(pl_move, color);
-            let sc = -self.negamax(new_board, depth - 1, -beta, -alpha, !color);
-            if sc > best {
-                best = sc;
+        for &index in order {
+            let pl_move = &allowed_moves[index];
+            let nodes_before = negamax.nodes;
+            let undo = game.play(&Some(pl_move.clone()));
+            let (score, line) = negamax.search(&mut game, depth, -beta, -alpha);
+            game.undo(undo);
+            if negamax.stopped {
+                return None;
+            }
+            let score = -score;
+            report.root_moves.push(RootMoveStats {
+                point: pl_move.0,
+                visits: negamax.nodes - nodes_before,
+                wins: 0,
+                score: score as f64,
+            });
+            if score > best_move.0 {
+                best_move = (score, index, line);
+            }
+
+            // Picking among root moves needs their exact scores
+            if self.level.is_none_or(|level| level.temperature == 0.0) {
+                alpha = max_of(alpha, best_move.0);
             }
-            alpha = max_of(alpha, best);
             if alpha >= beta {
                 break;
             }
         }
 
-        best
+        let (score, index, line) = best_move;
+        // The line stops at a pass, it has no point to show
+        let pv = std::iter::once(allowed_moves[index].0)
+            .chain(line.into_iter().map_while(|m| m.map(|m| m.0)))
+            .map(|p| (p, score as f64))
+            .collect();
+        report.depth = depth + 1;
+        report.best_score = score as f64;
+        report.pv = Variation(pv);
+        Some((index, report))
+    }
+
+    /// Static evaluation for the side to move at the leaves of a search
+    /// `depth` plies deep, with the level's noise
+    fn evaluate(&self, game: &Reversi, depth: usize) -> Score {
+        let mul = if game.to_move() == self.my_color {
+            1
+        } else {
+            -1
+        };
+        let even_depth = depth.is_multiple_of(2);
+        let board = &game.position.board;
+        let mut score =
+            sev3(board, self.my_color, even_depth) * self.variant.sign();
+        if let Some(level) = self.level.filter(|l| l.eval_noise > 0) {
+            let noise = level.eval_noise;
+            score += rng::thread_rng().gen_range(-noise, noise + 1);
+        }
+        score * mul
     }
 }
 
 impl Bot for MinimaxBot {
     fn status(&self) -> EndState {
-        self.win_state
+        let allowed_moves = self.allowed_tiles();
+        wincheck(
+            &self.board,
+            &allowed_moves,
+            self.variant,
+            self.current_color,
+        )
     }
     fn allowed_tiles(&self) -> AllowedMoves {
         self.board.allowed_moves(self.current_color)
     }
     fn apply_move(&mut self, player_move: &PlayerMove) {
-        self.board.apply_move(&player_move, self.current_color);
+        self.board.apply_move(player_move, self.current_color);
     }
     fn current_color(&self) -> Cell {
         self.current_color
@@ -218,10 +303,112 @@ impl Bot for MinimaxBot {
     fn self_color(&self) -> Cell {
         self.my_color
     }
-    fn run_ai(&self) -> PlayerMove {
-        self.run_negamax()
+    fn new_game(&mut self, position: Position, my_color: Cell) {
+        self.board = position.board;
+        self.current_color = position.to_move;
+        self.my_color = my_color;
+    }
+    fn run_ai(&self) -> (PlayerMove, SearchReport) {
+        let result = self.run_negamax();
+        match self.level {
+            Some(level) => level.apply(result, &self.allowed_tiles()),
+            None => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::Engine;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::time::Duration;
+
+    #[test]
+    fn best_root_score() {
+        let args = ["reversi_bot", "--max-depth", "3", "--board-size", "6"];
+        let matches = app().get_matches_from(args.iter());
+        let config = BotConfig::from_args(&matches).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        for plies in 4..24 {
+            // Random openings where black has a choice of moves
+            let mut game = Reversi {
+                position: Position::initial(6, &[]).unwrap(),
+                variant: Variant::Anti,
+            };
+            for _ in 0..plies {
+                let moves = game.legal_moves();
+                game.play(&moves[rng.gen_range(0, moves.len())]);
+            }
+            let moves = game.position.allowed_moves();
+            if game.to_move() != Cell::Black || moves.len() < 2 {
+                continue;
+            }
+
+            let bot = MinimaxBot::new(&config, game.position, Cell::Black);
+            let (_, report) = bot.run_negamax();
+            // Each root move searched with the full window
+            let mut negamax = Negamax::new(|g: &Reversi| bot.evaluate(g, 3));
+            let best = moves
+                .into_iter()
+                .map(|pl_move| {
+                    let mut next = game;
+                    next.play(&Some(pl_move));
+                    let (score, _) =
+                        negamax.search(&mut next, 3, -Score::MAX, Score::MAX);
+                    -score
+                })
+                .max()
+                .unwrap();
+            assert_eq!(report.best_score, best as f64, "after {}", plies);
+        }
+    }
+
+    #[test]
+    fn new_game_starts_over() {
+        let limits = Limits {
+            time: None,
+            playouts: None,
+        };
+        let config = BotConfig::new(Engine::Minimax, Variant::Anti, limits);
+        let position = Position::initial(8, &[]).unwrap();
+        let mut bot = MinimaxBot::new(&config, position, Cell::Black);
+        let (pl_move, _) = bot.run_ai();
+        bot.apply_move(&pl_move);
+        bot.set_color(Cell::White);
+
+        let next = Position::initial(6, &[]).unwrap();
+        bot.new_game(next, Cell::White);
+        assert!(bot.board == next.board);
+        assert_eq!(bot.self_color(), Cell::White);
+        assert_eq!(bot.current_color(), Cell::Black);
+        let (pl_move, _) = bot.run_ai();
+        assert!(next.allowed_moves().iter().any(|m| m.0 == pl_move.0));
     }
-    fn get_logfile(&self) -> LogFile {
-        self.log_file.clone()
+
+    #[test]
+    fn deepens_within_limits() {
+        let position = Position::initial(8, &[]).unwrap();
+        let limits = Limits {
+            time: Some(Duration::from_millis(100)),
+            playouts: None,
+        };
+        let mut config = BotConfig::new(Engine::Minimax, Variant::Anti, limits);
+        config.max_depth = 30;
+        let bot = MinimaxBot::new(&config, position, Cell::Black);
+        let now = Instant::now();
+        let (_, report) = bot.run_negamax();
+        assert!(now.elapsed() < Duration::from_secs(2));
+        assert!(report.depth > 1 && report.depth < 31);
+
+        // A node budget stops it as well, the first search always finishes
+        config.limits = Limits {
+            time: None,
+            playouts: Some(1),
+        };
+        let bot = MinimaxBot::new(&config, position, Cell::Black);
+        let (_, report) = bot.run_negamax();
+        assert_eq!(report.depth, 1);
+        assert_eq!(report.root_moves.len(), 4);
     }
 }
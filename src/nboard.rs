@@ -0,0 +1,407 @@
+use crate::{
+    arena::bot_config,
+    search::Engine,
+    utils::{
+        board::Board,
+        config::BotConfig,
+        negamax::WIN_SCORE,
+        point::Point,
+        position::Position,
+        report::{RootMoveStats, SearchReport},
+        variant::Variant,
+        *,
+    },
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+/// Points of the minimax static evaluation taken as one disc in hints
+const EVAL_PER_DISC: f64 = 50.0;
+
+/// Minimax score of a proven win
+const PROVEN_WIN: f64 = WIN_SCORE as f64;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("nboard")
+        .about("Talk the NBoard engine protocol on stdin/stdout")
+        .arg(
+            Arg::with_name("bot")
+                .long("bot")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .default_value("")
+                .help("Options of the bot, e.g. \"--bot-impl minimax\""),
+        )
+        .arg(
+            Arg::with_name("anti")
+                .long("anti")
+                .help("Play anti reversi instead of Othello"),
+        )
+}
+
+/// Parses a move like `F5`, `f5/1.00/0.5` or `PA`, `None` is a pass
+fn parse_move(s: &str) -> Result<Option<Point>, String> {
+    let mv = s.split('/').next().unwrap_or_default();
+    match mv.to_uppercase().as_str() {
+        "PA" | "PASS" => Ok(None),
+        _ => Point::from_ab(mv)
+            .map(Some)
+            .ok_or(format!("Bad move: {}", s)),
+    }
+}
+
+fn move_name(point: Option<Point>) -> String {
+    point.map_or("PA".to_string(), Point::to_ab)
+}
+
+/// Parses the board `BO[8 ---...*O... *]` of a GGF game, tiles row by row
+/// and then the side to move
+fn parse_board(s: &str) -> Result<Position, String> {
+    let mut tokens = s.split_whitespace();
+    let size = tokens
+        .next()
+        .and_then(|it| it.parse::<TileIdx>().ok())
+        .filter(|&size| Board::is_valid_size(size))
+        .ok_or(format!("Bad board: {}", s))?;
+    let mut board = Board::new(size, &[])?;
+    let mut tiles = tokens.by_ref().flat_map(str::chars);
+    for p in board.points().collect::<Vec<_>>() {
+        let cell = match tiles.next() {
+            Some('-') | Some('.') => Cell::Empty,
+            Some('*') | Some('X') | Some('x') => Cell::Black,
+            Some('O') | Some('o') => Cell::White,
+            _ => return Err(format!("Bad board: {}", s)),
+        };
+        board.place(p, cell);
+    }
+    let to_move = match tiles.next() {
+        Some('*') | Some('X') | Some('x') => Cell::Black,
+        Some('O') | Some('o') => Cell::White,
+        _ => return Err(format!("Bad side to move: {}", s)),
+    };
+    Ok(Position { board, to_move })
+}
+
+/// Position at the end of a GGF game like
+/// `(;GM[Othello]BO[8 ... *]B[F5]W[d6//0.1];)`
+pub fn parse_ggf(s: &str) -> Result<Position, String> {
+    let mut position = None;
+    let mut rest = s;
+    while let Some(open) = rest.find('[') {
+        let close = rest[open..]
+            .find(']')
+            .ok_or("Unterminated property".to_string())?
+            + open;
+        let name = rest[..open]
+            .rsplit(|ch: char| !ch.is_ascii_alphabetic())
+            .next()
+            .unwrap_or_default();
+        let value = &rest[open + 1..close];
+        rest = &rest[close + 1..];
+        let color = match name {
+            "BO" => {
+                position = Some(parse_board(value)?);
+                continue;
+            }
+            "B" => Cell::Black,
+            "W" => Cell::White,
+            _ => continue,
+        };
+        let position = position
+            .as_mut()
+            .ok_or("Move before the board".to_string())?;
+        // GGF may leave out passes
+        if position.to_move != color {
+            position.play(None)?;
+        }
+        position.play(parse_move(value)?)?;
+    }
+    position.ok_or("Game has no board".to_string())
+}
+
+/// State of the protocol: the game the GUI has set up so far
+pub struct NBoard {
+    config: BotConfig,
+    position: Option<Position>,
+}
+
+impl NBoard {
+    pub fn new(config: BotConfig) -> Self {
+        Self {
+            config,
+            position: None,
+        }
+    }
+
+    fn search(&self, position: Position) -> Result<SearchReport, String> {
+        let report = self.config.search(position)?;
+        info!(Search, "{}", report);
+        Ok(report)
+    }
+
+    fn position(&self) -> Result<Position, String> {
+        self.position.ok_or("No game has been set".to_string())
+    }
+
+    /// Lines to send back for a command, the best move of `go` is searched
+    /// with the bot's own limits. `go` and `hint` are answered even when
+    /// they fail, the GUI waits for them
+    pub fn handle(&mut self, line: &str) -> Result<Vec<String>, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let args = words.collect::<Vec<_>>();
+        match (command, args.first().copied()) {
+            ("set", Some("game")) => {
+                let ggf = line.splitn(3, ' ').nth(2).unwrap_or_default();
+                self.position = Some(parse_ggf(ggf)?);
+            }
+            ("move", Some(mv)) => {
+                let mut position = self.position()?;
+                let point = parse_move(mv)?;
+                if point.is_some() && position.allowed_moves().is_empty() {
+                    position.play(None)?;
+                }
+                position.play(point)?;
+                self.position = Some(position);
+            }
+            ("go", _) => return Ok(self.go()),
+            ("hint", n) => return Ok(self.hints(n)),
+            ("set", Some("depth")) => {
+                let depth = args.get(1).copied().unwrap_or_default();
+                self.config.max_depth = depth
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&depth| depth > 0)
+                    .ok_or(format!("Bad depth: {}", depth))?;
+            }
+            ("ping", n) => {
+                return Ok(vec![format!("pong {}", n.unwrap_or("0"))])
+            }
+            ("learn", _) => return Ok(vec!["learned".to_string()]),
+            // Handshake, engine name and contempt need no answer
+            ("nboard", _) | ("set", _) => {}
+            _ => return Err(format!("Unsupported command: {}", line)),
+        }
+        Ok(Vec::new())
+    }
+
+    fn best_move(&self, position: Position) -> Result<Option<Point>, String> {
+        if position.allowed_moves().is_empty() {
+            return Ok(None);
+        }
+        Ok(self.search(position)?.best_move())
+    }
+
+    /// The searched move, or any legal one when the search fails. Without
+    /// a game there is no move to send, only the reason
+    fn go(&self) -> Vec<String> {
+        let position = match self.position() {
+            Ok(position) => position,
+            Err(e) => {
+                error!(Protocol, "{}", e);
+                return vec![format!("status {}", e)];
+            }
+        };
+        let point = self.best_move(position).unwrap_or_else(|e| {
+            error!(Protocol, "{}", e);
+            position.allowed_moves().first().map(|m| m.0)
+        });
+        vec![
+            "status Thinking".to_string(),
+            format!("=== {}", move_name(point)),
+            "status".to_string(),
+        ]
+    }
+
+    /// Hints for the best `n` moves, none when that fails
+    fn hints(&self, n: Option<&str>) -> Vec<String> {
+        let mut lines = vec!["status Analyzing".to_string()];
+        match self.hint_lines(n.unwrap_or_default()) {
+            Ok(hints) => lines.extend(hints),
+            Err(e) => error!(Protocol, "{}", e),
+        }
+        lines.push("status".to_string());
+        lines
+    }
+
+    /// The bot's scores of `moves` as disc margins for the side to move.
+    /// MCTS win rates span the board from a sure loss to a sure win.
+    /// Minimax evaluations shift with the parity of the search depth, so
+    /// they are taken relative to the best unproven move, and proven
+    /// results span the board
+    fn evals(&self, moves: &[RootMoveStats], squares: f64) -> Vec<f64> {
+        let proven = |score: f64| score.abs() >= PROVEN_WIN;
+        let best = moves
+            .iter()
+            .map(|m| m.score)
+            .filter(|&score| !proven(score))
+            .fold(f64::MIN, f64::max);
+        let eval = |score: f64| match self.config.engine {
+            _ if score.is_nan() => 0.0,
+            Engine::Minimax if proven(score) => score.signum() * squares,
+            Engine::Minimax => (score - best) / EVAL_PER_DISC,
+            _ => (2.0 * score - 1.0) * squares,
+        };
+        let clamp = |discs: f64| discs.clamp(-squares, squares);
+        moves.iter().map(|m| clamp(eval(m.score))).collect()
+    }
+
+    /// Best `n` moves, the chosen one first and the others by the bot's
+    /// own score of them
+    fn hint_lines(&self, n: &str) -> Result<Vec<String>, String> {
+        let n = n
+            .parse::<usize>()
+            .map_err(|_| format!("Bad number of hints: {}", n))?;
+        let position = self.position()?;
+        let mut lines = Vec::new();
+        if position.allowed_moves().is_empty() {
+            if !position.is_over() {
+                lines.push("search PA 0 0 0".to_string());
+            }
+        } else {
+            let report = self.search(position)?;
+            let mut moves = report.root_moves.clone();
+            moves.sort_by(|a, b| {
+                b.score
+                    .partial_cmp(&a.score)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            if let Some(i) = moves
+                .iter()
+                .position(|m| Some(m.point) == report.best_move())
+            {
+                let best = moves.remove(i);
+                moves.insert(0, best);
+            }
+            moves.truncate(n);
+            let size = position.board.size as f64;
+            let evals = self.evals(&moves, size * size);
+            for (m, eval) in moves.iter().zip(evals) {
+                lines.push(format!(
+                    "search {} {:.2} 0 {}",
+                    m.point.to_ab(),
+                    eval,
+                    report.depth
+                ));
+            }
+        }
+        Ok(lines)
+    }
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), String> {
+    let spec = matches.value_of("bot").unwrap();
+    // NBoard plays Othello, anti reversi has to be asked for
+    let variant = if matches.is_present("anti") {
+        Variant::Anti
+    } else {
+        Variant::Normal
+    };
+    let config = bot_config(spec, variant)?;
+    let mut nboard = NBoard::new(config);
+    loop {
+        let line = input();
+        debug!(Protocol, "<- {}", line);
+        if line == "quit" {
+            return Ok(());
+        }
+        match nboard.handle(&line) {
+            Ok(lines) => {
+                for line in lines {
+                    debug!(Protocol, "-> {}", line);
+                    println!("{}", line);
+                }
+            }
+            Err(e) => error!(Protocol, "{}", e),
+        }
+        logger::flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::limits::Limits;
+
+    const START: &str = "(;GM[Othello]PC[NBoard]PB[me]PW[you]RE[?]TI[5:00]\
+        TY[8]BO[8 ---------------------------O*------*O--------------------------- *]\
+        B[F5//1.2]W[d6];)";
+
+    #[test]
+    fn ggf_games() {
+        let position = parse_ggf(START).unwrap();
+        let mut expected = Position::initial(8, &[]).unwrap();
+        expected.play_sequence("F5 D6").unwrap();
+        assert!(position.board == expected.board);
+        assert_eq!(position.to_move, Cell::Black);
+
+        assert!(parse_ggf("(;GM[Othello]B[F5];)").is_err());
+        assert!(parse_ggf(&START.replace("d6", "A1")).is_err());
+    }
+
+    #[test]
+    fn protocol() {
+        let spec = "--bot-impl minimax --max-depth 2";
+        let config = bot_config(spec, Variant::Normal).unwrap();
+        let mut nboard = NBoard::new(config);
+        // Answered without a game, the GUI would wait forever
+        let lines = nboard.handle("go").unwrap();
+        assert_eq!(lines, vec!["status No game has been set"]);
+        let lines = nboard.handle("hint 2").unwrap();
+        assert_eq!(lines, vec!["status Analyzing", "status"]);
+        assert_eq!(nboard.handle("ping 3").unwrap(), vec!["pong 3"]);
+        nboard.handle(&format!("set game {}", START)).unwrap();
+        nboard.handle("move c3/0.5/1").unwrap();
+        assert_eq!(nboard.position.unwrap().to_move, Cell::White);
+
+        let lines = nboard.handle("go").unwrap();
+        assert_eq!(lines.len(), 3);
+        let point = Point::from_ab(lines[1].trim_start_matches("=== "));
+        let allowed = nboard.position.unwrap().allowed_moves();
+        assert!(allowed.iter().any(|m| Some(m.0) == point));
+
+        nboard.handle("set depth 3").unwrap();
+        assert_eq!(nboard.config.max_depth, 3);
+        assert!(nboard.handle("set depth x").is_err());
+        let lines = nboard.handle("hint 2").unwrap();
+        assert_eq!(lines.len(), 4);
+        let evals = lines[1..3]
+            .iter()
+            .map(|line| line.split(' ').nth(2).unwrap().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert!(evals[0] == 0.0 && evals[1] <= 0.0);
+        assert!(lines[1].ends_with(" 0 4"));
+    }
+
+    #[test]
+    fn hint_evals_in_discs() {
+        let limits = Limits {
+            time: None,
+            playouts: Some(100),
+        };
+        let config = BotConfig::new(Engine::Mcts, Variant::Normal, limits);
+        let moves = |scores: &[f64]| {
+            let stats = |(x, &score)| RootMoveStats {
+                point: Point::from_xy(x as TileIdx, 0),
+                visits: 10,
+                wins: 0,
+                score,
+            };
+            scores.iter().enumerate().map(stats).collect::<Vec<_>>()
+        };
+        let nboard = NBoard::new(config);
+        let evals = nboard.evals(&moves(&[1.0, 0.25, f64::NAN]), 64.0);
+        assert_eq!(evals, vec![64.0, -32.0, 0.0]);
+
+        let config = BotConfig {
+            engine: Engine::Minimax,
+            ..config
+        };
+        let nboard = NBoard::new(config);
+        let evals = nboard.evals(&moves(&[-1500.0, -2000.0]), 64.0);
+        assert_eq!(evals, vec![0.0, -10.0]);
+        let evals =
+            nboard.evals(&moves(&[PROVEN_WIN, 300.0, -PROVEN_WIN]), 36.0);
+        assert_eq!(evals, vec![36.0, 0.0, -36.0]);
+    }
+}
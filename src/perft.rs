@@ -0,0 +1,213 @@
+use crate::utils::{board::Board, position::Position, *};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::time::Instant;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("perft")
+        .about("Count positions reachable in N plies to check move generation")
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .takes_value(true)
+                .default_value("6")
+                .help("Deepest ply to count"),
+        )
+        .arg(
+            Arg::with_name("black_holes")
+                .long("black-holes")
+                .takes_value(true)
+                .help("Black holes, e.g. \"D3 F6\""),
+        )
+        .arg(
+            Arg::with_name("each_hole")
+                .long("each-hole")
+                .help("Count once per empty tile with a black hole on it"),
+        )
+}
+
+/// Number of leaves `depth` plies below the position; a pass is a ply
+/// of its own and a finished game is a leaf wherever it ends
+pub fn perft(board: &mut Board, color: Cell, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let allowed_moves = board.allowed_moves(color);
+    if allowed_moves.is_empty() {
+        if board.allowed_moves(!color).is_empty() {
+            return 1;
+        }
+        let undo = board.make_pass(color);
+        let nodes = perft(board, !color, depth - 1);
+        board.unmake_move(undo);
+        return nodes;
+    }
+    let mut nodes = 0;
+    for pl_move in allowed_moves.iter() {
+        let undo = board.make_move(pl_move, color);
+        nodes += perft(board, !color, depth - 1);
+        board.unmake_move(undo);
+    }
+    nodes
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), String> {
+    let depth = matches
+        .value_of("depth")
+        .unwrap()
+        .parse::<usize>()
+        .map_err(|_| "Bad depth".to_string())?;
+    let black_holes = match matches.value_of("black_holes") {
+        Some(s) => parse_coords(s)?,
+        None => Vec::new(),
+    };
+    let position = Position::from_args(matches, &black_holes)?;
+
+    if matches.is_present("each_hole") {
+        let board = position.board;
+        for p in board.points().filter(|&p| board.at(p).is_empty()) {
+            let mut with_hole = position;
+            with_hole.add_black_holes(&[p])?;
+            let nodes = perft(&mut with_hole.board, with_hole.to_move, depth);
+            println!("{}: {}", p.to_ab(), nodes);
+        }
+        return Ok(());
+    }
+
+    let mut board = position.board;
+    for d in 1..=depth {
+        let now = Instant::now();
+        let nodes = perft(&mut board, position.to_move, d);
+        let elapsed = now.elapsed();
+        println!(
+            "perft({}) = {} ({}ms, {:.0} nodes/s)",
+            d,
+            nodes,
+            elapsed.as_millis(),
+            nodes as f64 / elapsed.as_secs_f64()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::point::Point;
+    use std::convert::TryFrom;
+
+    // Standard 8x8 reversi, passes counted as plies
+    const PERFT_8X8: [u64; 8] = [1, 4, 12, 56, 244, 1396, 8200, 55092];
+
+    /// Straightforward move generator to check `get_allowed_moves` against
+    fn naive_perft(board: &Board, color: Cell, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = board
+            .points()
+            .filter(|&p| board.at(p).is_empty())
+            .filter_map(|p| {
+                let flips = naive_flips(board, p, color);
+                if flips.is_empty() {
+                    None
+                } else {
+                    Some((p, flips))
+                }
+            })
+            .collect::<Vec<_>>();
+        if moves.is_empty() {
+            let opponent_moves = board
+                .points()
+                .any(|p| !naive_flips(board, p, !color).is_empty());
+            if !opponent_moves {
+                return 1;
+            }
+            return naive_perft(board, !color, depth - 1);
+        }
+        moves
+            .iter()
+            .map(|m| naive_perft(&board.with_move(m, color), !color, depth - 1))
+            .sum()
+    }
+
+    fn naive_flips(board: &Board, p: Point, color: Cell) -> Vec<Point> {
+        if !board.at(p).is_empty() {
+            return Vec::new();
+        }
+        let (x, y) = p.to_xy();
+        let mut flips = Vec::new();
+        for (dx, dy) in TRAVERSE_DIRECTIONS.iter() {
+            let mut ray = Vec::new();
+            let (mut x, mut y) = (x + dx, y + dy);
+            while x >= 0 && y >= 0 && x < board.size && y < board.size {
+                let q = Point::from_xy(x, y);
+                if board.at(q) == !color {
+                    ray.push(q);
+                } else {
+                    if board.at(q) == color {
+                        flips.extend(ray);
+                    }
+                    break;
+                }
+                x += dx;
+                y += dy;
+            }
+        }
+        flips
+    }
+
+    #[test]
+    fn perft_initial() {
+        let mut board = Board::new(8, &[]).unwrap();
+        for (depth, &expected) in PERFT_8X8.iter().enumerate() {
+            assert_eq!(perft(&mut board, Cell::Black, depth), expected);
+        }
+        assert!(board == Board::new(8, &[]).unwrap());
+    }
+
+    #[test]
+    fn perft_each_hole() {
+        for &size in [4, 6, 8].iter() {
+            let empty = Board::new(size, &[]).unwrap();
+            for p in empty.points().filter(|&p| empty.at(p).is_empty()) {
+                let mut board = Board::new(size, &[p]).unwrap();
+                assert_eq!(
+                    perft(&mut board, Cell::Black, 4),
+                    naive_perft(&board, Cell::Black, 4),
+                    "hole at {}",
+                    p.to_ab()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn perft_hole_symmetry() {
+        // The starting position is symmetric under the half turn
+        let last = 7;
+        let board = Board::new(8, &[]).unwrap();
+        for p in board.points().filter(|&p| board.at(p).is_empty()) {
+            let (x, y) = p.to_xy();
+            let rotated = Point::from_xy(last - x, last - y);
+            assert_eq!(
+                perft(&mut Board::new(8, &[p]).unwrap(), Cell::Black, 5),
+                perft(&mut Board::new(8, &[rotated]).unwrap(), Cell::Black, 5)
+            );
+        }
+    }
+
+    #[test]
+    fn perft_passes() {
+        // Black has to pass; after white's D4 the game is over,
+        // after C4 black passes again
+        let s = "WWWW
+                 WWWW
+                 WWBB
+                 WW__";
+        let mut board = Board::try_from(s.to_string()).unwrap();
+        assert_eq!(perft(&mut board, Cell::Black, 1), 1);
+        assert_eq!(perft(&mut board, Cell::Black, 2), 2);
+        assert_eq!(perft(&mut board, Cell::Black, 3), 2);
+        assert_eq!(naive_perft(&board, Cell::Black, 3), 2);
+    }
+}
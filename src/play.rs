@@ -0,0 +1,167 @@
+use crate::{
+    arena::parse_bot_spec,
+    utils::{
+        config::BotConfig, point::Point, position::Position, variant::Variant,
+        *,
+    },
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::io::{stdout, Write};
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("play")
+        .about("Play against a bot in the terminal")
+        .arg(
+            Arg::with_name("bot")
+                .long("bot")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .default_value("")
+                .help("Options of the bot, e.g. \"--bot-impl minimax\""),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .takes_value(true)
+                .possible_values(&["black", "white"])
+                .default_value("black")
+                .help("Color of the human player"),
+        )
+        .arg(
+            Arg::with_name("black_holes")
+                .long("black-holes")
+                .takes_value(true)
+                .help("Black holes, e.g. \"D3 F6\""),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), String> {
+    let config = parse_bot_spec(matches.value_of("bot").unwrap(), matches)?;
+    let human = match matches.value_of("color").unwrap() {
+        "black" => Cell::Black,
+        _ => Cell::White,
+    };
+    let black_holes = match matches.value_of("black_holes") {
+        Some(s) => parse_coords(s)?,
+        None => Vec::new(),
+    };
+    let variant = Variant::from_args(matches);
+
+    let mut position = Position::from_args(matches, &black_holes)?;
+    // Positions before each of the human's moves, for undo
+    let mut history = Vec::new();
+    loop {
+        let allowed_moves = position.allowed_moves();
+        let state = wincheck(
+            &position.board,
+            &allowed_moves,
+            variant,
+            position.to_move,
+        );
+        if state.is_over() {
+            print!("{}", repr_board(&position.board));
+            println!("{}", result_message(&position, state, human));
+            return Ok(());
+        }
+
+        if position.to_move != human {
+            if allowed_moves.is_empty() {
+                println!("Bot passes");
+                position.play(None)?;
+                continue;
+            }
+            let bot = create_bot(&config, position, !human);
+            let (pl_move, _) = bot.run_ai();
+            println!("Bot plays {}", pl_move.0.to_ab());
+            position.play(Some(pl_move.0))?;
+            continue;
+        }
+
+        let moves = allowed_moves.iter().map(|m| m.0).collect::<Vec<_>>();
+        print!("{}", repr_board_with_moves(&position.board, &moves));
+        println!(
+            "Black: {}, white: {}",
+            position.board.count(Cell::Black),
+            position.board.count(Cell::White)
+        );
+        if moves.is_empty() {
+            print!("No moves, pass/undo/resign: ");
+        } else {
+            print!("Your move (A1, undo, hint, resign): ");
+        }
+        stdout().flush().ok();
+
+        let before = position;
+        match input().to_lowercase().as_str() {
+            "undo" => match history.pop() {
+                Some(prev) => position = prev,
+                None => println!("Nothing to undo"),
+            },
+            "hint" => println!("{}", hint(&config, position)),
+            "resign" => {
+                println!("You resigned");
+                return Ok(());
+            }
+            "pass" => match position.play(None) {
+                Ok(()) => history.push(before),
+                Err(e) => println!("{}", e),
+            },
+            other => match Point::from_ab(other) {
+                Some(p) => match position.play(Some(p)) {
+                    Ok(()) => history.push(before),
+                    Err(e) => println!("{}", e),
+                },
+                None => println!("Unknown command: {}", other),
+            },
+        }
+    }
+}
+
+/// The bot's move for the human, who may only have a pass
+fn hint(config: &BotConfig, position: Position) -> String {
+    if position.allowed_moves().is_empty() {
+        return "Hint: pass".to_string();
+    }
+    let bot = create_bot(config, position, position.to_move);
+    let (pl_move, report) = bot.run_ai();
+    format!("Hint: {} ({})", pl_move.0.to_ab(), report.pv)
+}
+
+fn result_message(position: &Position, state: EndState, human: Cell) -> String {
+    let score = format!(
+        "black {}, white {}",
+        position.board.count(Cell::Black),
+        position.board.count(Cell::White)
+    );
+    if state == EndState::Tie {
+        format!("Tie: {}", score)
+    } else if state.won(human) {
+        format!("You won: {}", score)
+    } else {
+        format!("Bot won: {}", score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hint_to_pass() {
+        // Black has no move but white has
+        let s = "WWWW
+                 WWWW
+                 BBWW
+                 B___";
+        let mut position = Position::parse(s).unwrap();
+        let args =
+            ["reversi_bot", "--bot-impl", "minimax", "--board-size", "4"];
+        let matches = app().get_matches_from(args.iter());
+        let config = BotConfig::from_args(&matches).unwrap();
+        // Searching would panic on a position without moves
+        assert_eq!(hint(&config, position), "Hint: pass");
+        position.to_move = Cell::White;
+        let white_hint = hint(&config, position);
+        assert!(white_hint.starts_with("Hint: ") && white_hint != "Hint: pass");
+    }
+}
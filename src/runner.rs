@@ -1,20 +1,36 @@
-use std::io::Write;
+use std::time::Instant;
 
 use crate::{
     bot::Bot,
-    utils::{CLIMove, Chan},
+    utils::{logger, point::Point, position::Position, CLIMove, Cell, Chan},
 };
 
+/// Our color and the black holes of the game to play next
+pub type NewGame = (Cell, Vec<Point>);
+
 pub struct Runner {
     bot: Box<dyn Bot>,
+    // The opponent's reply the bot's last search was expecting
+    expected_reply: Option<Point>,
 }
 
 impl Runner {
     pub fn new(bot: Box<dyn Bot>) -> Self {
-        Self { bot }
+        Self {
+            bot,
+            expected_reply: None,
+        }
     }
 
-    pub fn run(&mut self) {
+    /// Sets the bot up for the next game from `position`
+    pub fn new_game(&mut self, position: Position, my_color: Cell) {
+        self.bot.new_game(position, my_color);
+        self.expected_reply = None;
+    }
+
+    /// Plays the game to the end. A `newgame` command from the opponent
+    /// abandons it and is returned for the caller to start
+    pub fn run(&mut self) -> Option<NewGame> {
         loop {
             let bot = &mut self.bot;
             let allowed_moves = bot.allowed_tiles();
@@ -23,47 +39,67 @@ impl Runner {
             let is_self_move = cur_color == bot.self_color();
 
             if win_state.is_over() {
-                break;
+                return None;
             }
 
-            if allowed_moves.len() > 0 {
+            if !allowed_moves.is_empty() {
                 if is_self_move {
-                    let pl_move = bot.run_ai();
+                    let now = Instant::now();
+                    let (pl_move, report) = bot.run_ai();
+                    info!(Search, "{}", report);
+                    if logger::json_enabled() {
+                        logger::write_json(&report.to_json());
+                    }
+                    info!(Time, "move took {}ms", now.elapsed().as_millis());
+                    self.expected_reply = report.pv.expected_reply();
                     bot.apply_move(&pl_move);
                     Chan::send(CLIMove::Coord(pl_move.0));
                 } else {
                     let pl_move = loop {
-                        let coord = Chan::read().coord();
+                        let coord = match Chan::read() {
+                            CLIMove::NewGame(color, black_holes) => {
+                                return Some((color, black_holes));
+                            }
+                            cli_move => cli_move.coord(),
+                        };
                         let pl_move =
                             allowed_moves.iter().find(|(ti, _)| *ti == coord);
                         if let Some(pl_move) = pl_move {
                             break pl_move;
                         }
+                        error!(Protocol, "illegal move: {}", coord.to_ab());
                     };
-                    bot.apply_move(&pl_move);
+                    if let Some(expected) = self.expected_reply.take() {
+                        if expected != pl_move.0 {
+                            info!(
+                                Search,
+                                "opponent played {}, expected {}",
+                                pl_move.0.to_ab(),
+                                expected.to_ab()
+                            );
+                        }
+                    }
+                    bot.apply_move(pl_move);
                 }
             } else {
                 if is_self_move {
                     Chan::send(CLIMove::Pass);
                 } else {
-                    Chan::read();
+                    if let CLIMove::NewGame(color, black_holes) = Chan::read() {
+                        return Some((color, black_holes));
+                    }
+                    self.expected_reply = None;
                 }
             }
             bot.set_color(cur_color.opposite());
-            self.flush_logs();
-        }
-    }
-
-    fn flush_logs(&mut self) {
-        if let Some(logfile) = self.bot.get_logfile() {
-            let mut lck = logfile.lock().unwrap();
-            lck.flush().unwrap()
+            logger::flush();
         }
     }
 
+    #[allow(dead_code)]
     fn report(&self) {
-        // log!(
-        //     self,
+        // info!(
+        //     Search,
         //     "{}",
         //     match self.win_state {
         //         EndState::Tie => "Tie!",
@@ -72,9 +108,6 @@ impl Runner {
         //         _ => "Game hadn't been completed.",
         //     }
         // );
-        // if let Some(logfile) = &self.bot.get_logfile() {
-        //     let mut lck = logfile.lock().unwrap();
-        //     lck.flush().unwrap();
-        // }
+        // logger::flush();
     }
 }
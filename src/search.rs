@@ -0,0 +1,55 @@
+use crate::utils::{
+    config::BotConfig, limits::Limits, position::Position,
+    report::SearchReport, variant::Variant,
+};
+
+/// Search engines, the same as the `--bot-impl` choices
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+    Minimax,
+    MctsBasic,
+    Mcts,
+    MctsMinimax,
+    MctsRave,
+}
+
+impl Engine {
+    pub const ALL: [Engine; 5] = [
+        Engine::Minimax,
+        Engine::MctsBasic,
+        Engine::Mcts,
+        Engine::MctsMinimax,
+        Engine::MctsRave,
+    ];
+
+    /// Engine of a `--bot-impl` name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Engine::ALL
+            .iter()
+            .copied()
+            .find(|engine| engine.name() == name)
+    }
+
+    /// Name of the engine on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Minimax => "minimax",
+            Engine::MctsBasic => "mcts_basic",
+            Engine::Mcts => "mcts",
+            Engine::MctsMinimax => "mcts_minimax",
+            Engine::MctsRave => "mcts_rave",
+        }
+    }
+
+    /// Searches `position` for the side to move with the engine's default
+    /// settings, see `BotConfig::search`. Minimax deepens its search until
+    /// the limits stop it, counting nodes against the playout budget
+    pub fn search(
+        self,
+        position: Position,
+        variant: Variant,
+        limits: Limits,
+    ) -> Result<SearchReport, String> {
+        BotConfig::new(self, variant, limits).search(position)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    arena::parse_bot_spec,
+    runner::{NewGame, Runner},
+    utils::{position::Position, *},
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("session")
+        .about("Play consecutive games on stdin/stdout, each after `newgame`")
+        .arg(
+            Arg::with_name("bot")
+                .long("bot")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .default_value("")
+                .help("Options of the bot, e.g. \"--bot-impl minimax\""),
+        )
+}
+
+/// Waits for the next `newgame`, other commands and unreadable lines
+/// between games are skipped
+fn read_new_game() -> NewGame {
+    loop {
+        let line = input();
+        debug!(Protocol, "<- {}", line);
+        match CLIMove::parse(&line) {
+            Some(CLIMove::NewGame(color, black_holes)) => {
+                return (color, black_holes)
+            }
+            Some(_) => info!(Protocol, "no game in progress, command ignored"),
+            None => error!(Protocol, "unexpected command: {}", line),
+        }
+    }
+}
+
+/// Plays games until stdin is closed
+pub fn run(matches: &ArgMatches) -> Result<(), String> {
+    let config = parse_bot_spec(matches.value_of("bot").unwrap(), matches)?;
+    // Broken --position or --moves would spoil every game
+    Position::from_args(matches, &[])?;
+    // One bot plays every game, it is set up again on `newgame`
+    let mut runner: Option<Runner> = None;
+    let mut next_game = None;
+    for game in 1.. {
+        let (color, position) = loop {
+            let (color, black_holes) =
+                next_game.take().unwrap_or_else(read_new_game);
+            info!(
+                Protocol,
+                "game {}: black holes: {:?}, my color: {:?}",
+                game,
+                black_holes.iter().map(|p| p.to_ab()).collect::<Vec<_>>(),
+                color
+            );
+            match Position::from_args(matches, &black_holes) {
+                Ok(position) => break (color, position),
+                Err(e) => error!(Protocol, "newgame refused: {}", e),
+            }
+        };
+        if let Some(runner) = &mut runner {
+            runner.new_game(position, color);
+        }
+        let runner = runner.get_or_insert_with(|| {
+            Runner::new(create_bot(&config, position, color))
+        });
+        next_game = runner.run();
+        if next_game.is_some() {
+            info!(Protocol, "game {} abandoned", game);
+        }
+        logger::flush();
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use crate::{
+    arena::{parse_bot_spec, play_game},
+    utils::{
+        board::Board,
+        elo::{self, Record},
+        point::Point,
+        position::Position,
+        variant::Variant,
+        *,
+    },
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use rand::seq::SliceRandom;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("tournament")
+        .about("Rate bot configurations by playing them against each other")
+        .arg(
+            Arg::with_name("bot")
+                .long("bot")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(true)
+                .help("Options of a participant, repeat for every bot"),
+        )
+        .arg(
+            Arg::with_name("gauntlet")
+                .long("gauntlet")
+                .help("Only pair the first bot with each of the others"),
+        )
+        .arg(
+            Arg::with_name("games")
+                .long("games")
+                .takes_value(true)
+                .default_value("10")
+                .help("Games per pairing, even, the most games with --sprt"),
+        )
+        .arg(
+            Arg::with_name("random_holes")
+                .long("random-holes")
+                .takes_value(true)
+                .default_value("0")
+                .help("Number of random black holes, new for every game pair"),
+        )
+        .arg(
+            Arg::with_name("sprt")
+                .long("sprt")
+                .takes_value(true)
+                .help("Stop once \"elo0,elo1\" is decided for two bots"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), String> {
+    let specs = matches.values_of("bot").unwrap().collect::<Vec<_>>();
+    if specs.len() < 2 {
+        return Err("At least two bots are needed".to_string());
+    }
+    let configs = specs
+        .iter()
+        .map(|spec| parse_bot_spec(spec, matches))
+        .collect::<Result<Vec<_>, _>>()?;
+    let ngames = parse_number(matches, "games")?;
+    if ngames % 2 != 0 {
+        // Every opening is played once with each color
+        return Err(format!("Odd number of games: {}", ngames));
+    }
+    let nholes = parse_number(matches, "random_holes")?;
+    let sprt = match matches.value_of("sprt") {
+        Some(s) if specs.len() == 2 => Some(parse_sprt(s)?),
+        Some(_) => return Err("SPRT needs exactly two bots".to_string()),
+        None => None,
+    };
+    let variant = Variant::from_args(matches);
+
+    let pairings = if matches.is_present("gauntlet") {
+        (1..specs.len()).map(|j| (0, j)).collect::<Vec<_>>()
+    } else {
+        (0..specs.len())
+            .flat_map(|i| (i + 1..specs.len()).map(move |j| (i, j)))
+            .collect()
+    };
+    let mut records = vec![Record::default(); pairings.len()];
+    let mut rng = rng::thread_rng();
+    let (lower, upper) = elo::sprt_bounds(0.05, 0.05);
+
+    'rounds: for round in 0..ngames / 2 {
+        for (&(i, j), record) in pairings.iter().zip(records.iter_mut()) {
+            let black_holes =
+                random_holes(board_size(matches), nholes, &mut rng);
+            let position = Position::from_args(matches, &black_holes)?;
+            for &(black, white) in [(i, j), (j, i)].iter() {
+                let bots = [
+                    create_bot(&configs[black], position, Cell::Black),
+                    create_bot(&configs[white], position, Cell::White),
+                ];
+                let result = play_game(bots, position, variant);
+                let i_color =
+                    if black == i { Cell::Black } else { Cell::White };
+                if result == EndState::Tie {
+                    record.draws += 1;
+                } else if result.won(i_color) {
+                    record.wins += 1;
+                } else {
+                    record.losses += 1;
+                }
+                println!(
+                    "round {}: black \"{}\", white \"{}\": {:?}",
+                    round + 1,
+                    specs[black],
+                    specs[white],
+                    result
+                );
+            }
+        }
+        if let Some((elo0, elo1)) = sprt {
+            let llr = records[0].sprt_llr(elo0, elo1);
+            println!("LLR: {:.2} ({:.2}, {:.2})", llr, lower, upper);
+            if llr >= upper {
+                println!("H1 accepted: \"{}\" is stronger", specs[0]);
+                break 'rounds;
+            }
+            if llr <= lower {
+                println!("H0 accepted: \"{}\" isn't stronger", specs[0]);
+                break 'rounds;
+            }
+        }
+    }
+
+    let rated = pairings
+        .iter()
+        .zip(records.iter())
+        .map(|(&(i, j), &record)| (i, j, record))
+        .collect::<Vec<_>>();
+    let ratings = elo::ratings(&rated, specs.len());
+    let margins = elo::rating_margins(&rated, &ratings);
+    let mut order = (0..specs.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| ratings[b].partial_cmp(&ratings[a]).unwrap());
+
+    println!(
+        "{:>4} {:>8} {:>7} {:>6}  bot",
+        "rank", "elo", "+/-", "games"
+    );
+    for (rank, &p) in order.iter().enumerate() {
+        let mut total = Record::default();
+        for &(i, j, record) in rated.iter() {
+            if i == p {
+                total.add(record);
+            } else if j == p {
+                total.add(record.reversed());
+            }
+        }
+        println!(
+            "{:>4} {:>8.1} {:>7.1} {:>6}  {}",
+            rank + 1,
+            ratings[p],
+            margins[p],
+            total.games(),
+            specs[p]
+        );
+    }
+    Ok(())
+}
+
+fn parse_number(matches: &ArgMatches, name: &str) -> Result<usize, String> {
+    let value = matches.value_of(name).unwrap();
+    value
+        .parse::<usize>()
+        .map_err(|_| format!("Bad number: {}", value))
+}
+
+fn parse_sprt(s: &str) -> Result<(f64, f64), String> {
+    let bounds = s
+        .split(',')
+        .map(|b| b.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("Bad SPRT bounds: {}", s))?;
+    match bounds.as_slice() {
+        &[elo0, elo1] if elo0 < elo1 => Ok((elo0, elo1)),
+        _ => Err(format!("Bad SPRT bounds: {}", s)),
+    }
+}
+
+/// Holes on tiles that are empty at the start of the game
+fn random_holes(
+    size: TileIdx,
+    nholes: usize,
+    rng: &mut impl rand::Rng,
+) -> Vec<Point> {
+    let board = Board::new(size, &[]).unwrap();
+    let empty = board
+        .points()
+        .filter(|&p| board.at(p).is_empty())
+        .collect::<Vec<_>>();
+    empty.choose_multiple(rng, nholes).cloned().collect()
+}
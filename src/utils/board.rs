@@ -1,12 +1,30 @@
 use crate::utils::*;
-use point::Point;
-use rand::{prelude::*, rngs::ThreadRng, Rng};
+use point::{Point, MAX_CELLS, MAX_SIZE};
 use std::fmt;
 
-use super::sev::sev3;
+use super::zobrist;
 
-#[derive(Copy, Clone)]
-pub struct Board(pub [Cell; 64]);
+pub const DEFAULT_SIZE: TileIdx = 8;
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Board {
+    /// Indexed by `Point`, only the top left `size` x `size` square is used.
+    /// Room for 10x10 costs 8x8 boards a few percent at most, within the
+    /// noise of perft and playout counts against 64 cells
+    pub cells: [Cell; MAX_CELLS],
+    pub size: TileIdx,
+    /// Zobrist hash of the cells, kept up to date by every change
+    pub hash: u64,
+}
+
+/// Everything `Board::unmake_move` needs to take back a move or a pass
+pub struct Undo {
+    /// `None` for a pass
+    point: Option<Point>,
+    /// One bit per flipped tile, indexed by `Point`
+    flipped: u128,
+    color: Cell,
+}
 
 #[derive(Copy, Clone)]
 pub enum MainLine {
@@ -28,172 +46,164 @@ pub const MAINLINES: &[MainLine] = &[
 ];
 
 impl Board {
-    pub fn initial(black_hole: Option<Point>) -> Self {
-        let mut board = Board([Cell::Empty; 64]);
+    /// Standard 8x8 starting position
+    #[allow(dead_code)]
+    pub fn initial(black_holes: &[Point]) -> Result<Self, String> {
+        Self::new(DEFAULT_SIZE, black_holes)
+    }
+
+    /// Starting position on a `size` x `size` board, black holes have to
+    /// be on the board and off the starting discs
+    pub fn new(size: TileIdx, black_holes: &[Point]) -> Result<Self, String> {
+        assert!(Self::is_valid_size(size), "Unsupported board size");
+        let mut board = Board {
+            cells: [Cell::Empty; MAX_CELLS],
+            size,
+            hash: 0,
+        };
+        let (lo, hi) = (size / 2 - 1, size / 2);
         board
-            .place(Point::from_xy(3, 3), Cell::White)
-            .place(Point::from_xy(4, 4), Cell::White)
-            .place(Point::from_xy(3, 4), Cell::Black)
-            .place(Point::from_xy(4, 3), Cell::Black);
-        if let Some(bh) = black_hole {
-            board.place(bh, Cell::BlackHole);
+            .place(Point::from_xy(lo, lo), Cell::White)
+            .place(Point::from_xy(hi, hi), Cell::White)
+            .place(Point::from_xy(lo, hi), Cell::Black)
+            .place(Point::from_xy(hi, lo), Cell::Black);
+        for &bh in black_holes {
+            board.add_black_hole(bh)?;
         }
-        board
+        Ok(board)
+    }
+
+    /// Turns an empty tile into a black hole
+    pub fn add_black_hole(&mut self, p: Point) -> Result<(), String> {
+        if !self.contains(p) {
+            return Err(format!(
+                "Black hole {} is off the {1}x{1} board",
+                p.to_ab(),
+                self.size
+            ));
+        }
+        match self.at(p) {
+            Cell::Empty => {
+                self.place(p, Cell::BlackHole);
+                Ok(())
+            }
+            Cell::BlackHole => {
+                Err(format!("Black hole {} is given twice", p.to_ab()))
+            }
+            _ => Err(format!("Black hole {} is on a disc", p.to_ab())),
+        }
+    }
+
+    pub fn is_valid_size(size: TileIdx) -> bool {
+        (4..=MAX_SIZE).contains(&size) && size % 2 == 0
     }
 
     pub fn place(&mut self, p: Point, color: Cell) -> &mut Self {
-        self.0[p.usize()] = color;
+        self.hash ^= zobrist::key(p, self.at(p)) ^ zobrist::key(p, color);
+        self.cells[p.usize()] = color;
         self
     }
 
+    #[inline]
+    pub fn contains(&self, p: Point) -> bool {
+        let (x, y) = p.to_xy();
+        x < self.size && y < self.size
+    }
+
+    /// All tiles of the board, row by row
+    #[inline]
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let size = self.size;
+        (0..size)
+            .flat_map(move |y| (0..size).map(move |x| Point::from_xy(x, y)))
+    }
+
     #[inline]
     pub fn apply_move(
         &mut self,
         player_move: &PlayerMove,
         color: Cell,
     ) -> &mut Self {
-        self.0[player_move.0.usize()] = color;
-        for i in player_move.1.iter() {
-            self.0[i.usize()] = color;
+        let (point, flipped) = player_move;
+        self.cells[point.usize()] = color;
+        self.hash ^= zobrist::key(*point, color);
+        for &p in flipped.iter() {
+            self.cells[p.usize()] = color;
+            self.hash ^= zobrist::flip_key(p);
         }
         self
     }
 
+    /// Applies the move in place and returns what's needed to take it back
     #[inline]
-    pub fn allowed_moves(&self, color: Cell) -> AllowedMoves {
-        get_allowed_moves(&self, color)
-    }
-
-    pub fn at(&self, point: Point) -> Cell {
-        self.0[point.usize()]
-    }
-
-    pub fn count(&self, color: Cell) -> usize {
-        self.0.iter().filter(|&&c| c == color).count()
-    }
-
-    #[inline]
-    pub fn with_move(&self, player_move: &PlayerMove, color: Cell) -> Self {
-        let mut result = self.clone();
-        result.apply_move(player_move, color);
-        result
+    pub fn make_move(&mut self, player_move: &PlayerMove, color: Cell) -> Undo {
+        let flipped = player_move
+            .1
+            .iter()
+            .fold(0, |bits, p| bits | 1 << p.usize());
+        let undo = Undo {
+            point: Some(player_move.0),
+            flipped,
+            color,
+        };
+        self.apply_move(player_move, color);
+        undo
     }
 
+    /// A pass leaves the board as it is, but still has to be taken back
+    /// in the same order as the moves around it
     #[inline]
-    pub fn sim(
-        board: &Board,
-        player_move: PlayerMove,
-        mut color: Cell,
-        is_anti: bool,
-        mut rng: ThreadRng,
-    ) -> EndState {
-        let mut new_board = board.with_move(&player_move, color);
-        loop {
-            color = color.opposite();
-            let mut allowed = new_board.allowed_moves(color);
-            let win = wincheck(&new_board, &allowed, is_anti, color);
-            if win.is_over() {
-                return win;
-            }
-            if allowed.len() == 0 {
-                color = color.opposite();
-                allowed = new_board.allowed_moves(color);
-            }
-            let mv = allowed[rng.gen_range(0, allowed.len())].clone();
-            new_board.apply_move(&mv, color);
+    pub fn make_pass(&self, color: Cell) -> Undo {
+        Undo {
+            point: None,
+            flipped: 0,
+            color,
         }
     }
 
     #[inline]
-    pub fn simauto(
-        mut board: Board,
-        mut color: Cell,
-        is_anti: bool,
-    ) -> EndState {
-        let mut rng = thread_rng();
-        loop {
-            let mut allowed = board.allowed_moves(color);
-            if allowed.len() == 1 {
-                board.apply_move(&allowed.first().unwrap(), color);
-                color = color.opposite();
-                continue;
+    pub fn unmake_move(&mut self, undo: Undo) {
+        if let Some(point) = undo.point {
+            self.cells[point.usize()] = Cell::Empty;
+            self.hash ^= zobrist::key(point, undo.color);
+            let mut flipped = undo.flipped;
+            while flipped != 0 {
+                let p = Point::from_idx(flipped.trailing_zeros() as TileIdx);
+                self.cells[p.usize()] = !undo.color;
+                self.hash ^= zobrist::flip_key(p);
+                flipped &= flipped - 1;
             }
-            let win = wincheck(&board, &allowed, is_anti, color);
-            if win.is_over() {
-                return win;
-            }
-            if allowed.is_empty() {
-                color = color.opposite();
-                allowed = board.allowed_moves(color);
-            }
-            let mv = allowed[rng.gen_range(0, allowed.len())].clone();
-            board.apply_move(&mv, color);
-            color = color.opposite();
         }
     }
 
     #[inline]
-    pub fn sim_with_sev(
-        mut board: Board,
-        mut color: Cell,
-        is_anti: bool,
-        is_depth_even: bool,
-        bot_color: Cell,
-    ) -> EndState {
-        // let mut rng = thread_rng();
-        loop {
-            let mut allowed = board.allowed_moves(color);
-
-            if allowed.len() == 1 {
-                board.apply_move(&allowed.first().unwrap(), color);
-                color = !color;
-                continue;
-            }
-
-            let win = wincheck(&board, &allowed, is_anti, color);
-
-            if win.is_over() {
-                return win;
-            }
+    pub fn allowed_moves(&self, color: Cell) -> AllowedMoves {
+        get_allowed_moves(self, color)
+    }
 
-            if allowed.is_empty() {
-                // Pass
-                color = !color;
-                allowed = board.allowed_moves(color);
-            }
+    pub fn at(&self, point: Point) -> Cell {
+        self.cells[point.usize()]
+    }
 
-            let (mut best_move, mut best_score) = {
-                let fst_move = allowed.first().unwrap();
-                let board = board.with_move(fst_move, color);
-                let best_score = sev3(&board, bot_color, is_depth_even);
-                (fst_move, best_score)
-            };
-            let is_maxing = is_anti ^ (bot_color == color);
-
-            for player_move in allowed.iter() {
-                let temp_board = board.with_move(player_move, color);
-                let sev_score = sev3(&temp_board, bot_color, is_depth_even);
-                if (is_maxing && sev_score > best_score)
-                    || (!is_maxing && sev_score < best_score)
-                {
-                    best_move = player_move;
-                    best_score = sev_score;
-                }
-            }
+    pub fn count(&self, color: Cell) -> usize {
+        self.points().filter(|&p| self.at(p) == color).count()
+    }
 
-            // let mv = allowed[rng.gen_range(0, allowed.len())].clone();
-            // board.apply_move(&mv, color);
-            board.apply_move(best_move, color);
-            color = !color;
-        }
+    #[inline]
+    pub fn with_move(&self, player_move: &PlayerMove, color: Cell) -> Self {
+        let mut result = *self;
+        result.apply_move(player_move, color);
+        result
     }
 
     #[inline]
     pub fn nempty_neighbours(&self, pos: Point) -> i32 {
         let (px, py) = pos.to_xy();
+        let last = self.size - 1;
         let mut res = 0;
-        for y in if py > 0 { -1 } else { 0 }..=if py < 7 { 1 } else { 0 } {
-            for x in if px > 0 { -1 } else { 0 }..=if px < 7 { 1 } else { 0 } {
+        for y in if py > 0 { -1 } else { 0 }..=if py < last { 1 } else { 0 } {
+            for x in if px > 0 { -1 } else { 0 }..=if px < last { 1 } else { 0 }
+            {
                 if x == 0 && y == 0 {
                     continue;
                 }
@@ -208,15 +218,16 @@ impl Board {
     #[inline]
     pub fn mainline(&self, mainline: MainLine) -> Vec<Cell> {
         use MainLine::*;
-        let f = match mainline {
-            Top => |i| (i, 0),
-            Left => |i| (0, i),
-            Right => |i| (7, i),
-            Bottom => |i| (i, 7),
-            TopLeftBottomRight => |i| (i, i),
-            TopRightBottomLeft => |i| (7 - i, i),
+        let last = self.size - 1;
+        let f = move |i| match mainline {
+            Top => (i, 0),
+            Left => (0, i),
+            Right => (last, i),
+            Bottom => (i, last),
+            TopLeftBottomRight => (i, i),
+            TopRightBottomLeft => (last - i, i),
         };
-        (0..8)
+        (0..self.size)
             .map(f)
             .map(|(x, y)| self.at(Point::from_xy(x, y)))
             .collect()
@@ -225,16 +236,16 @@ impl Board {
 
 impl fmt::Debug for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", repr_board(&self.0))
+        write!(f, "{}", repr_board(self))
     }
 }
 
 impl std::convert::TryFrom<String> for Board {
     type Error = String;
 
+    /// Board size is deduced from the number of tiles
     fn try_from(board_str: String) -> Result<Self, Self::Error> {
-        let mut board = [Cell::Empty; 64];
-        let mut idx = 0;
+        let mut cells = Vec::with_capacity(MAX_CELLS);
         for ch in board_str.chars().filter(|ch| !ch.is_whitespace()) {
             let cell = match ch {
                 'B' => Some(Cell::Black),
@@ -245,30 +256,44 @@ impl std::convert::TryFrom<String> for Board {
             };
 
             if let Some(cell) = cell {
-                board[idx] = cell;
-                idx += 1;
+                cells.push(cell);
             } else {
                 return Err(format!("Unexpected char inside board: {}", ch));
             }
         }
+        let size = (1..=MAX_SIZE)
+            .find(|size| (size * size) as usize == cells.len())
+            .filter(|&size| Board::is_valid_size(size))
+            .ok_or(format!("Unexpected number of tiles: {}", cells.len()))?;
+        let mut board = Board {
+            cells: [Cell::Empty; MAX_CELLS],
+            size,
+            hash: 0,
+        };
+        for (p, cell) in board.points().zip(cells) {
+            board.place(p, cell);
+        }
         // let disccount = board.iter().filter(|d| d.is_disc()).count()
-        Ok(Self(board))
+        Ok(board)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::position::Position;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::convert::TryFrom;
     #[test]
     fn test_board_count() {
-        let board = Board::initial(Some(Point::from_xy(0, 0)));
+        let board = Board::initial(&[Point::from_xy(0, 0)]).unwrap();
         assert_eq!(board.count(Cell::Black), 2);
         assert_eq!(board.count(Cell::White), 2);
     }
 
     #[test]
     fn test_board_count_move1() {
-        let mut board = Board::initial(Some(Point::from_xy(0, 0)));
+        let mut board = Board::initial(&[Point::from_xy(0, 0)]).unwrap();
         let allowed_moves = board.allowed_moves(Cell::White);
         board.apply_move(allowed_moves.first().unwrap(), Cell::White);
         assert_eq!(board.count(Cell::Black), 1);
@@ -277,7 +302,7 @@ mod tests {
 
     #[test]
     fn test_board_count_move2() {
-        let board = Board::initial(Some(Point::from_xy(0, 0)));
+        let board = Board::initial(&[Point::from_xy(0, 0)]).unwrap();
         let allowed_moves = board.allowed_moves(Cell::Black);
         let board =
             board.with_move(allowed_moves.first().unwrap(), Cell::Black);
@@ -287,16 +312,96 @@ mod tests {
 
     #[test]
     fn test_board_indexes() {
-        let b = Board::initial(Some(Point::from_xy(1, 1)));
-        for (i, _) in b.0.iter().enumerate() {
-            let tile_index = Point::from_idx(i as TileIdx).unmirror8().to_idx();
-            assert!([0, 1, 2, 3, 9, 10, 11, 18, 19, 27].contains(&tile_index));
+        let b = Board::initial(&[Point::from_xy(1, 1)]).unwrap();
+        for p in b.points() {
+            let (x, y) = p.unmirror8(b.size).to_xy();
+            assert!(x < 4 && y <= x);
         }
+        assert_eq!(b.points().count(), 64);
+    }
+
+    #[test]
+    fn test_board_sizes() {
+        for &size in [6, 8, 10].iter() {
+            let board = Board::new(size, &[]).unwrap();
+            assert_eq!(board.points().count(), (size * size) as usize);
+            assert_eq!(board.count(Cell::Black), 2);
+            assert_eq!(board.count(Cell::White), 2);
+            assert_eq!(board.count(Cell::Empty), (size * size) as usize - 4);
+            assert_eq!(board.allowed_moves(Cell::Black).len(), 4);
+            assert_eq!(board.mainline(MainLine::Bottom).len(), size as usize);
+        }
+        let corner = Point::from_xy(9, 9);
+        assert_eq!(Board::new(10, &[]).unwrap().nempty_neighbours(corner), 3);
+    }
+
+    #[test]
+    fn test_multiple_black_holes() {
+        let holes = [Point::from_xy(0, 0), Point::from_xy(5, 2)];
+        let board = Board::initial(&holes).unwrap();
+        assert_eq!(board.count(Cell::BlackHole), 2);
+        // Holes on starting discs, off the board or twice are refused
+        let err = Board::new(6, &[Point::from_xy(2, 2)]).unwrap_err();
+        assert_eq!(err, "Black hole C3 is on a disc");
+        let err = Board::new(6, &[Point::from_xy(7, 7)]).unwrap_err();
+        assert_eq!(err, "Black hole H8 is off the 6x6 board");
+        let twice = [Point::from_xy(0, 0), Point::from_xy(0, 0)];
+        assert!(Board::new(6, &twice).is_err());
+    }
+
+    #[test]
+    fn test_ray_blocked_by_hole() {
+        let s = "________
+                 ________
+                 ________
+                 _BWHW___
+                 ________
+                 ________
+                 ________
+                 ________";
+        let board = Board::try_from(s.to_string()).unwrap();
+        // The hole at D4 stops the ray from B4 before it reaches E4
+        assert!(board.allowed_moves(Cell::Black).is_empty());
+        // and the one from E4 before it reaches B4
+        let moves = board.allowed_moves(Cell::White);
+        assert_eq!(moves.len(), 1);
+        assert!(moves[0].0 == Point::from_xy(0, 3));
+        assert!(moves[0].1 == vec![Point::from_xy(1, 3)]);
+    }
+
+    #[test]
+    fn test_move_onto_hole() {
+        let s = "________
+                 ________
+                 ___H____
+                 ___WB___
+                 ___BW___
+                 ________
+                 ________
+                 ________";
+        let board = Board::try_from(s.to_string()).unwrap();
+        let moves = board.allowed_moves(Cell::Black);
+        assert!(moves.iter().all(|(p, _)| board.at(*p).is_empty()));
+        assert_eq!(moves.len(), 3);
+    }
+
+    #[test]
+    fn test_board_from_str_size() {
+        let s = "______
+                 ______
+                 __WB__
+                 __BW__
+                 ______
+                 ______";
+        let board = Board::try_from(s.to_string()).unwrap();
+        assert_eq!(board.size, 6);
+        assert!(board.at(Point::from_xy(2, 2)) == Cell::White);
+        assert!(Board::try_from("BW_".to_string()).is_err());
     }
 
     #[test]
     fn test_empty_neighbours() {
-        let board = Board::initial(Some(Point::from_xy(0, 0)));
+        let board = Board::initial(&[Point::from_xy(0, 0)]).unwrap();
         assert_eq!(board.nempty_neighbours(Point::from_xy(3, 4)), 5);
         assert_eq!(board.nempty_neighbours(Point::from_xy(5, 5)), 7);
     }
@@ -304,7 +409,7 @@ mod tests {
     #[test]
     fn test_board_mainline() {
         use Cell::*;
-        let board = Board::initial(Some(Point::from_xy(0, 0)));
+        let board = Board::initial(&[Point::from_xy(0, 0)]).unwrap();
         assert_eq!(
             board.mainline(MainLine::TopLeftBottomRight),
             vec![BlackHole, Empty, Empty, White, White, Empty, Empty, Empty],
@@ -322,4 +427,41 @@ mod tests {
             vec![Empty, Empty, Empty, Black, Black, Empty, Empty, Empty],
         );
     }
+
+    #[test]
+    fn test_make_unmake_round_trip() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for game in 0..40 {
+            let size = [4, 6, 8, 10][game % 4];
+            let hole = Point::from_xy(rng.gen_range(0, size), 0);
+            let mut board = Board::new(size, &[hole]).unwrap();
+            let mut color = Cell::Black;
+            let mut history = Vec::new();
+            loop {
+                let moves = board.allowed_moves(color);
+                let before = board;
+                let undo = if !moves.is_empty() {
+                    let pl_move = &moves[rng.gen_range(0, moves.len())];
+                    board.make_move(pl_move, color)
+                } else if !board.allowed_moves(!color).is_empty() {
+                    board.make_pass(color)
+                } else {
+                    break;
+                };
+                history.push((before, undo));
+                color = !color;
+                let position = Position {
+                    board,
+                    to_move: color,
+                };
+                assert_eq!(position.hash(), zobrist::hash(&position));
+            }
+            while let Some((before, undo)) = history.pop() {
+                board.unmake_move(undo);
+                assert!(board == before);
+                assert_eq!(board.hash, before.hash);
+            }
+            assert!(board == Board::new(size, &[hole]).unwrap());
+        }
+    }
 }
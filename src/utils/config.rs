@@ -0,0 +1,164 @@
+use super::{
+    create_bot,
+    final_move::FinalMove,
+    level::Level,
+    limits::Limits,
+    position::Position,
+    report::SearchReport,
+    rollout::{Policy, Rollout},
+    tree2::{Minimax, Priors},
+    variant::Variant,
+};
+use crate::search::Engine;
+use clap::ArgMatches;
+
+/// Settings a bot is built from, parsed from its command line or filled
+/// in by library users
+#[derive(Clone, Copy, Debug)]
+pub struct BotConfig {
+    pub engine: Engine,
+    pub variant: Variant,
+    /// Handicap that overrides `max_depth` and shortens the time limit
+    pub level: Option<Level>,
+    /// When a search stops, the level's share of the time already taken
+    pub limits: Limits,
+    /// Depth of the deepest minimax iteration
+    pub max_depth: usize,
+    pub final_move: FinalMove,
+    /// Playout policy, `None` for the engine's own default
+    pub rollout: Option<Policy>,
+    /// Moves after which playouts stop and get evaluated
+    pub rollout_cutoff: Option<usize>,
+    /// UCT exploration constant, `None` for the square root of 2
+    pub exploitation_value: Option<f64>,
+    pub priors: Priors,
+    pub minimax: Minimax,
+    /// Share of the disc margin in MCTS tree playout rewards
+    pub margin_weight: f64,
+    /// Visits at which RAVE and UCT weigh the same
+    pub rave_k: f64,
+}
+
+impl BotConfig {
+    /// Command line defaults of `engine` playing `variant` within `limits`
+    pub fn new(engine: Engine, variant: Variant, limits: Limits) -> Self {
+        Self {
+            engine,
+            variant,
+            level: None,
+            limits,
+            max_depth: 4,
+            final_move: FinalMove::MaxRatio,
+            rollout: None,
+            rollout_cutoff: None,
+            exploitation_value: None,
+            priors: Priors {
+                bias: 0.0,
+                widening: false,
+            },
+            minimax: Minimax {
+                weight: 0.3,
+                threshold: 5,
+            },
+            margin_weight: 0.0,
+            rave_k: 500.0,
+        }
+    }
+
+    pub fn from_args(matches: &ArgMatches) -> Result<Self, String> {
+        let engine = matches
+            .value_of("bot_impl")
+            .and_then(Engine::from_name)
+            .unwrap();
+        let level = Level::from_args(matches);
+        let parse = |name| {
+            matches
+                .value_of(name)
+                .map(|it| it.parse::<f64>().unwrap())
+                .unwrap()
+        };
+        let config = Self {
+            engine,
+            variant: Variant::from_args(matches),
+            level,
+            limits: Limits::from_args(matches, level),
+            max_depth: matches
+                .value_of("max_depth")
+                .map(|s| s.parse::<usize>().unwrap())
+                .unwrap(),
+            final_move: FinalMove::from_args(matches),
+            rollout: Policy::from_args(matches),
+            rollout_cutoff: matches
+                .value_of("rollout_cutoff")
+                .map(|it| it.parse::<usize>().unwrap()),
+            exploitation_value: matches
+                .value_of("exploitation_value")
+                .map(|it| it.parse::<f64>().unwrap()),
+            priors: Priors::from_args(matches),
+            minimax: Minimax::from_args(matches),
+            margin_weight: parse("margin_weight"),
+            rave_k: parse("rave_k"),
+        };
+        config.check()?;
+        Ok(config)
+    }
+
+    /// Refuses settings a search can't run with: MCTS needs a time limit
+    /// or a playout budget, minimax stops at its depth anyway
+    pub fn check(&self) -> Result<(), String> {
+        match self.engine {
+            Engine::Minimax => Ok(()),
+            _ => self.limits.check(),
+        }
+    }
+
+    /// Playouts of the configured policy, `default` when none is set
+    pub fn rollout(&self, default: Policy) -> Rollout {
+        Rollout {
+            policy: self.rollout.unwrap_or(default),
+            cutoff: self.rollout_cutoff,
+        }
+    }
+
+    /// Searches `position` for the side to move. The best move leads the
+    /// principal variation of the report, which is empty when the side to
+    /// move has to pass
+    pub fn search(&self, position: Position) -> Result<SearchReport, String> {
+        self.check()?;
+        if position.allowed_moves().is_empty() {
+            return Ok(SearchReport::new(position.to_move));
+        }
+        let bot = create_bot(self, position, position.to_move);
+        Ok(bot.run_ai().1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::app;
+    use std::time::Duration;
+
+    #[test]
+    fn defaults_match_command_line() {
+        let matches = app().get_matches_from(["reversi_bot"].iter());
+        let parsed = BotConfig::from_args(&matches).unwrap();
+        let limits = Limits {
+            time: Some(Duration::from_millis(4950)),
+            playouts: None,
+        };
+        let config = BotConfig::new(Engine::Mcts, Variant::Anti, limits);
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", config));
+    }
+
+    #[test]
+    fn endless_search_refused() {
+        let parse = |engine| {
+            let args =
+                ["reversi_bot", "--time-limit", "0", "--bot-impl", engine];
+            BotConfig::from_args(&app().get_matches_from(args.iter()))
+        };
+        assert!(parse("mcts").is_err());
+        assert!(parse("minimax").is_ok());
+    }
+}
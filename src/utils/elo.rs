@@ -0,0 +1,265 @@
+/// Results of one player against another
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Record {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Record {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Points per game, a draw is half a point
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    pub fn reversed(&self) -> Self {
+        Self {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+        }
+    }
+
+    pub fn add(&mut self, other: Record) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+
+    /// Variance of the points of a single game
+    fn variance(&self) -> f64 {
+        let n = self.games() as f64;
+        let s = self.score();
+        (self.wins as f64 * (1.0 - s).powi(2)
+            + self.draws as f64 * (0.5 - s).powi(2)
+            + self.losses as f64 * s.powi(2))
+            / n
+    }
+
+    /// Elo difference with the margin of its 95% confidence interval,
+    /// the margin is infinite while every game ended the same way
+    pub fn elo(&self) -> (f64, f64) {
+        let s = self.score();
+        if self.variance() == 0.0 {
+            return (elo_from_score(s), f64::INFINITY);
+        }
+        let stderr = (self.variance() / self.games() as f64).sqrt();
+        let lo = elo_from_score(s - 1.96 * stderr);
+        let hi = elo_from_score(s + 1.96 * stderr);
+        (elo_from_score(s), (hi - lo) / 2.0)
+    }
+
+    /// Log-likelihood ratio of "the difference is `elo1`" against
+    /// "the difference is `elo0`", using the normal approximation
+    pub fn sprt_llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let variance = self.variance();
+        if self.games() == 0 || variance == 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (score_from_elo(elo0), score_from_elo(elo1));
+        let s = self.score();
+        (s1 - s0) * (2.0 * s - s0 - s1) / (2.0 * variance / self.games() as f64)
+    }
+}
+
+/// Lower and upper LLR bounds for error rates `alpha` and `beta`
+pub fn sprt_bounds(alpha: f64, beta: f64) -> (f64, f64) {
+    ((beta / (1.0 - alpha)).ln(), ((1.0 - beta) / alpha).ln())
+}
+
+/// Scores of 0 and 1 are clamped, their difference would be infinite
+pub fn elo_from_score(score: f64) -> f64 {
+    let score = score.clamp(0.001, 0.999);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+pub fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Bradley-Terry ratings of `nplayers` from their pairwise records,
+/// scaled to Elo with an average of 0. Every pair gets one virtual draw
+/// so that unbeaten players still have a finite rating
+pub fn ratings(
+    records: &[(usize, usize, Record)],
+    nplayers: usize,
+) -> Vec<f64> {
+    let mut strength = vec![1.0; nplayers];
+    for _ in 0..1000 {
+        let mut next = strength.clone();
+        for (i, strength_i) in next.iter_mut().enumerate() {
+            let mut points = 0.0;
+            let mut denominator = 0.0;
+            for &(a, b, record) in records.iter() {
+                let (record, other) = if i == a {
+                    (record, b)
+                } else if i == b {
+                    (record.reversed(), a)
+                } else {
+                    continue;
+                };
+                let games = record.games() as f64 + 1.0;
+                points +=
+                    record.wins as f64 + (record.draws as f64 + 1.0) / 2.0;
+                denominator += games / (strength[i] + strength[other]);
+            }
+            if denominator > 0.0 {
+                *strength_i = points / denominator;
+            }
+        }
+        let mean_log =
+            next.iter().map(|s: &f64| s.ln()).sum::<f64>() / nplayers as f64;
+        strength = next.iter().map(|s| s / mean_log.exp()).collect();
+    }
+    strength.iter().map(|s| 400.0 * s.log10()).collect()
+}
+
+/// Margins of the 95% confidence intervals of the ratings fitted by
+/// `ratings`, from the curvature of the Bradley-Terry likelihood there
+pub fn rating_margins(
+    records: &[(usize, usize, Record)],
+    ratings: &[f64],
+) -> Vec<f64> {
+    let n = ratings.len();
+    let strength = ratings
+        .iter()
+        .map(|elo| 10f64.powf(elo / 400.0))
+        .collect::<Vec<_>>();
+    // Fisher information of the log strengths, plus the mean constraint
+    let mut info = vec![vec![1.0 / n as f64; n]; n];
+    for &(a, b, record) in records.iter() {
+        let p = strength[a] / (strength[a] + strength[b]);
+        let w = (record.games() as f64 + 1.0) * p * (1.0 - p);
+        info[a][a] += w;
+        info[b][b] += w;
+        info[a][b] -= w;
+        info[b][a] -= w;
+    }
+    let covariance = invert(info);
+    let elo_per_log = 400.0 / 10f64.ln();
+    (0..n)
+        .map(|i| {
+            let variance = covariance[i][i] - 1.0 / n as f64;
+            1.96 * variance.max(0.0).sqrt() * elo_per_log
+        })
+        .collect()
+}
+
+/// Gauss-Jordan inverse of a small nonsingular matrix
+fn invert(mut m: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    let n = m.len();
+    let mut inv = (0..n)
+        .map(|i| (0..n).map(|j| (i == j) as u8 as f64).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&a, &b| {
+                m[a][col].abs().partial_cmp(&m[b][col].abs()).unwrap()
+            })
+            .unwrap();
+        m.swap(col, pivot);
+        inv.swap(col, pivot);
+        let d = m[col][col];
+        for j in 0..n {
+            m[col][j] /= d;
+            inv[col][j] /= d;
+        }
+        for row in 0..n {
+            let f = m[row][col];
+            if row != col && f != 0.0 {
+                for j in 0..n {
+                    m[row][j] -= f * m[col][j];
+                    inv[row][j] -= f * inv[col][j];
+                }
+            }
+        }
+    }
+    inv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elo_score_conversion() {
+        assert!(elo_from_score(0.5).abs() < 1e-9);
+        assert!((elo_from_score(0.75) - 190.85).abs() < 0.01);
+        assert!((score_from_elo(elo_from_score(0.3)) - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn record_elo_interval() {
+        let record = Record {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+        };
+        let (elo, margin) = record.elo();
+        assert!((record.score() - 0.7).abs() < 1e-9);
+        assert!(elo > 140.0 && elo < 150.0);
+        assert!(margin > 0.0 && margin < elo);
+    }
+
+    #[test]
+    fn sprt() {
+        let (lo, hi) = sprt_bounds(0.05, 0.05);
+        assert!((hi - 2.944).abs() < 0.001 && (lo + 2.944).abs() < 0.001);
+        let winning = Record {
+            wins: 300,
+            draws: 100,
+            losses: 100,
+        };
+        assert!(winning.sprt_llr(0.0, 20.0) > hi);
+        assert!(winning.reversed().sprt_llr(0.0, 20.0) < lo);
+    }
+
+    #[test]
+    fn ratings_order() {
+        let even = Record {
+            wins: 5,
+            draws: 0,
+            losses: 5,
+        };
+        let strong = Record {
+            wins: 9,
+            draws: 0,
+            losses: 1,
+        };
+        let records = [(0, 1, strong), (1, 2, strong), (0, 2, strong)];
+        let elo = ratings(&records, 3);
+        assert!(elo[0] > elo[1] && elo[1] > elo[2]);
+        assert!(elo.iter().sum::<f64>().abs() < 1e-6);
+        let elo = ratings(&[(0, 1, even)], 2);
+        assert!(elo[0].abs() < 1e-6 && elo[1].abs() < 1e-6);
+    }
+
+    #[test]
+    fn margins_from_fit() {
+        let record = |wins, losses| Record {
+            wins,
+            draws: 0,
+            losses,
+        };
+        // With two players each rating is half the difference, and so is
+        // its margin
+        let records = [(0, 1, record(70, 30))];
+        let elo = ratings(&records, 2);
+        let margins = rating_margins(&records, &elo);
+        let (_, pooled) = record(70, 30).elo();
+        assert!((margins[0] - margins[1]).abs() < 1e-6);
+        assert!((2.0 * margins[0] / pooled - 1.0).abs() < 0.1);
+        // More games, narrower intervals
+        let more = [(0, 1, record(700, 300))];
+        let wide = rating_margins(&more, &ratings(&more, 2));
+        assert!(wide[0] < margins[0] / 3.0);
+        // A player in fewer pairings is known less precisely
+        let records = [(0, 1, record(50, 50)), (1, 2, record(50, 50))];
+        let margins = rating_margins(&records, &ratings(&records, 3));
+        assert!(margins[1] < margins[0] && margins[1] < margins[2]);
+    }
+}
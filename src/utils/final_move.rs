@@ -0,0 +1,249 @@
+use super::{
+    limits::Limits,
+    report::{RootMoveStats, SearchReport},
+    *,
+};
+use std::time::{Duration, Instant};
+
+/// Weight of the confidence interval of the secure child
+const SECURE_EXPLORATION: f64 = 1.0;
+
+/// Rounds of extended search max-robust runs before giving up
+const MAX_EXTENSIONS: usize = 2;
+
+/// How an MCTS bot picks its move from the root statistics
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FinalMove {
+    /// Most visits
+    Robust,
+    /// Highest score
+    MaxRatio,
+    /// Highest lower confidence bound of the score
+    Secure,
+    /// Most visits and highest score at once, the search goes on
+    /// while they are different moves
+    MaxRobust,
+}
+
+/// Search results of one root move
+pub struct RootSearch {
+    pub stats: RootMoveStats,
+    pub pv: Variation,
+    pub depth: usize,
+    /// The solver proved the move wins
+    pub proven_win: bool,
+}
+
+impl RootSearch {
+    /// Adds the results of another search of the same move
+    fn merge(&mut self, other: RootSearch) {
+        let (a, b) = (self.stats.visits as f64, other.stats.visits as f64);
+        if b > 0.0 {
+            self.stats.score = if a > 0.0 {
+                (a * self.stats.score + b * other.stats.score) / (a + b)
+            } else {
+                other.stats.score
+            };
+            self.pv = other.pv;
+        }
+        self.stats.visits += other.stats.visits;
+        self.stats.wins += other.stats.wins;
+        self.depth = self.depth.max(other.depth);
+        self.proven_win |= other.proven_win;
+    }
+}
+
+/// Report of a search that chose root move `chosen`
+pub fn report(
+    color: Cell,
+    chosen: usize,
+    roots: Vec<RootSearch>,
+) -> SearchReport {
+    let mut report = SearchReport::new(color);
+    for (index, root) in roots.into_iter().enumerate() {
+        if index == chosen {
+            report.best_score = root.stats.score;
+            report.pv = root.pv;
+        }
+        report.nodes += root.stats.visits;
+        report.depth = report.depth.max(root.depth);
+        report.root_moves.push(root.stats);
+    }
+    report
+}
+
+impl FinalMove {
+    pub fn from_args(matches: &ArgMatches) -> Self {
+        match matches.value_of("final_move").unwrap() {
+            "robust" => FinalMove::Robust,
+            "secure" => FinalMove::Secure,
+            "max-robust" => FinalMove::MaxRobust,
+            _ => FinalMove::MaxRatio,
+        }
+    }
+
+    /// Index of the move to play, a proven win comes first. `None` when
+    /// max-robust has no move with both the most visits and the best score
+    pub fn choose(&self, roots: &[RootSearch]) -> Option<usize> {
+        if let Some(index) = roots.iter().position(|r| r.proven_win) {
+            return Some(index);
+        }
+        let scored = (0..roots.len())
+            .filter(|&i| roots[i].stats.visits > 0)
+            .filter(|&i| !roots[i].stats.score.is_nan())
+            .collect::<Vec<_>>();
+        if scored.is_empty() {
+            return Some(0);
+        }
+        let total = scored.iter().map(|&i| roots[i].stats.visits).sum::<u64>();
+        let best_by = |key: &dyn Fn(&RootMoveStats) -> (f64, f64)| {
+            let cmp = |&&a: &&usize, &&b: &&usize| {
+                let (a, b) = (key(&roots[a].stats), key(&roots[b].stats));
+                a.partial_cmp(&b).unwrap()
+            };
+            *scored.iter().max_by(cmp).unwrap()
+        };
+        let robust = best_by(&|s| (s.visits as f64, s.score));
+        let max_ratio = best_by(&|s| (s.score, s.visits as f64));
+        match self {
+            FinalMove::Robust => Some(robust),
+            FinalMove::MaxRatio => Some(max_ratio),
+            FinalMove::Secure => Some(best_by(&|s| {
+                let reward = s.score * s.visits as f64;
+                let bounds =
+                    get_LCB_UCB(total, reward, s.visits, SECURE_EXPLORATION);
+                (bounds.0, s.visits as f64)
+            })),
+            FinalMove::MaxRobust if robust == max_ratio => Some(robust),
+            FinalMove::MaxRobust => None,
+        }
+    }
+
+    /// Runs `search` over all root moves and picks one. Max-robust
+    /// searches the most visited and the best scored moves again while
+    /// they differ, at most `MAX_EXTENSIONS` times, and then plays the
+    /// robust child. It keeps half of the time limit for that, the whole
+    /// move stays within the limit
+    pub fn search(
+        &self,
+        nmoves: usize,
+        limits: Limits,
+        mut search: impl FnMut(&[usize], Limits) -> Vec<RootSearch>,
+    ) -> (usize, Vec<RootSearch>) {
+        let now = Instant::now();
+        let first = match self {
+            FinalMove::MaxRobust => Limits {
+                time: limits.time.map(|time| time / 2),
+                ..limits
+            },
+            _ => limits,
+        };
+        let mut roots = search(&(0..nmoves).collect::<Vec<_>>(), first);
+        for round in 0..MAX_EXTENSIONS {
+            if self.choose(&roots).is_some() {
+                break;
+            }
+            let contested = [
+                FinalMove::Robust.choose(&roots).unwrap(),
+                FinalMove::MaxRatio.choose(&roots).unwrap(),
+            ];
+            let rounds = MAX_EXTENSIONS - round;
+            let extension = limits.extension(nmoves, now.elapsed(), rounds);
+            if extension.time == Some(Duration::ZERO) {
+                break;
+            }
+            let results = search(&contested, extension);
+            for (&index, result) in contested.iter().zip(results) {
+                roots[index].merge(result);
+            }
+        }
+        let chosen = self
+            .choose(&roots)
+            .unwrap_or_else(|| FinalMove::Robust.choose(&roots).unwrap());
+        (chosen, roots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(x: TileIdx, visits: u64, score: f64) -> RootSearch {
+        RootSearch {
+            stats: RootMoveStats {
+                point: Point::from_xy(x, 0),
+                visits,
+                wins: (visits as f64 * score) as u64,
+                score,
+            },
+            pv: Variation::default(),
+            depth: 1,
+            proven_win: false,
+        }
+    }
+
+    #[test]
+    fn policies() {
+        let roots = [root(0, 1000, 0.6), root(1, 5, 0.8), root(2, 200, 0.62)];
+        assert_eq!(FinalMove::Robust.choose(&roots), Some(0));
+        assert_eq!(FinalMove::MaxRatio.choose(&roots), Some(1));
+        assert_eq!(FinalMove::Secure.choose(&roots), Some(0));
+        assert_eq!(FinalMove::MaxRobust.choose(&roots), None);
+
+        let mut proven = [root(0, 1000, 0.6), root(1, 3, 0.3)];
+        proven[1].proven_win = true;
+        assert_eq!(FinalMove::Robust.choose(&proven), Some(1));
+    }
+
+    #[test]
+    fn max_robust_extends() {
+        let limits = Limits {
+            time: None,
+            playouts: Some(300),
+        };
+        let mut calls = Vec::new();
+        let (chosen, roots) =
+            FinalMove::MaxRobust.search(3, limits, |ix, l| {
+                calls.push((ix.to_vec(), l.playouts));
+                // The first search overrates move 1, more playouts fix it
+                let score = |i| match (i, calls.len()) {
+                    (1, 1) => 0.9,
+                    (1, _) => 0.1,
+                    _ => 0.5,
+                };
+                ix.iter()
+                    .map(|&i| root(i as TileIdx, [100, 10, 50][i], score(i)))
+                    .collect()
+            });
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[1], (vec![0, 1], Some(100)));
+        assert_eq!(chosen, 0);
+        assert_eq!(roots[1].stats.visits, 20);
+        assert!((roots[1].stats.score - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn max_robust_keeps_the_time_limit() {
+        let time = Duration::from_millis(200);
+        let limits = Limits {
+            time: Some(time),
+            playouts: None,
+        };
+        let now = Instant::now();
+        let mut calls = Vec::new();
+        FinalMove::MaxRobust.search(3, limits, |ix, l| {
+            calls.push(l.time.unwrap());
+            std::thread::sleep(l.time.unwrap());
+            // Move 0 keeps the most visits and move 2 the best score
+            ix.iter()
+                .map(|&i| {
+                    root(i as TileIdx, [100, 10, 50][i], 0.1 + 0.4 * i as f64)
+                })
+                .collect()
+        });
+        let elapsed = now.elapsed();
+        assert_eq!(calls.len(), 1 + MAX_EXTENSIONS);
+        assert_eq!(calls[0], time / 2);
+        assert!(elapsed < time + Duration::from_millis(10), "{:?}", elapsed);
+    }
+}
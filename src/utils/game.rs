@@ -0,0 +1,220 @@
+use super::{board::Undo, position::Position, variant::Variant, *};
+
+/// Rules of a two player game with alternating turns, everything the
+/// generic search engines know about the game they play
+pub trait Game: Clone {
+    type Move: Clone;
+    /// What `undo` needs to take a move back
+    type Undo;
+
+    /// Side to move
+    fn to_move(&self) -> Cell;
+
+    /// Moves of the side to move, none once the game is over
+    fn legal_moves(&self) -> Vec<Self::Move>;
+
+    /// Plays a legal move, the other side moves next
+    fn play(&mut self, player_move: &Self::Move) -> Self::Undo;
+
+    /// Takes back the last move played, the one that returned `undo`
+    fn undo(&mut self, undo: Self::Undo);
+
+    /// Winner of a finished game, `EndState::Unknown` while it goes on
+    fn result(&self) -> EndState;
+}
+
+/// Reversi or anti reversi, a pass is the `None` move
+#[derive(Clone, Copy)]
+pub struct Reversi {
+    pub position: Position,
+    pub variant: Variant,
+}
+
+impl Game for Reversi {
+    type Move = Option<PlayerMove>;
+    type Undo = Undo;
+
+    fn to_move(&self) -> Cell {
+        self.position.to_move
+    }
+
+    fn legal_moves(&self) -> Vec<Self::Move> {
+        let allowed = self.position.allowed_moves();
+        if !allowed.is_empty() {
+            allowed.into_iter().map(Some).collect()
+        } else if self.position.is_over() {
+            Vec::new()
+        } else {
+            vec![None]
+        }
+    }
+
+    fn play(&mut self, player_move: &Self::Move) -> Undo {
+        let color = self.position.to_move;
+        self.position.to_move = !color;
+        let board = &mut self.position.board;
+        match player_move {
+            Some(player_move) => board.make_move(player_move, color),
+            None => board.make_pass(color),
+        }
+    }
+
+    fn undo(&mut self, undo: Undo) {
+        self.position.to_move = !self.position.to_move;
+        self.position.board.unmake_move(undo);
+    }
+
+    fn result(&self) -> EndState {
+        let allowed = self.position.allowed_moves();
+        wincheck(&self.position.board, &allowed, self.variant, self.to_move())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Tic-tac-toe with black as X, the smallest game to test the engines
+    #[derive(Clone, Debug)]
+    pub struct TicTacToe {
+        pub cells: [Cell; 9],
+        pub to_move: Cell,
+    }
+
+    const LINES: [[usize; 3]; 8] = [
+        [0, 1, 2],
+        [3, 4, 5],
+        [6, 7, 8],
+        [0, 3, 6],
+        [1, 4, 7],
+        [2, 5, 8],
+        [0, 4, 8],
+        [2, 4, 6],
+    ];
+
+    /// Plays uniformly random moves until the game is over
+    pub fn random_playout<G: Game>(
+        mut game: G,
+        rng: &mut impl Rng,
+    ) -> EndState {
+        loop {
+            let moves = game.legal_moves();
+            if moves.is_empty() {
+                return game.result();
+            }
+            game.play(&moves[rng.gen_range(0, moves.len())]);
+        }
+    }
+
+    impl TicTacToe {
+        /// Parses rows of `X`, `O` and `_`, X moves first
+        pub fn parse(s: &str) -> Self {
+            let mut cells = [Cell::Empty; 9];
+            let tiles = s.chars().filter(|ch| !ch.is_whitespace());
+            for (cell, ch) in cells.iter_mut().zip(tiles) {
+                *cell = match ch {
+                    'X' => Cell::Black,
+                    'O' => Cell::White,
+                    _ => Cell::Empty,
+                };
+            }
+            let count = |c| cells.iter().filter(|&&it| it == c).count();
+            let to_move = if count(Cell::Black) > count(Cell::White) {
+                Cell::White
+            } else {
+                Cell::Black
+            };
+            Self { cells, to_move }
+        }
+    }
+
+    impl Game for TicTacToe {
+        type Move = usize;
+        type Undo = usize;
+
+        fn to_move(&self) -> Cell {
+            self.to_move
+        }
+
+        fn legal_moves(&self) -> Vec<usize> {
+            if self.result().is_over() {
+                return Vec::new();
+            }
+            (0..9).filter(|&i| self.cells[i].is_empty()).collect()
+        }
+
+        fn play(&mut self, &i: &usize) -> usize {
+            self.cells[i] = self.to_move;
+            self.to_move = !self.to_move;
+            i
+        }
+
+        fn undo(&mut self, i: usize) {
+            self.cells[i] = Cell::Empty;
+            self.to_move = !self.to_move;
+        }
+
+        fn result(&self) -> EndState {
+            for line in LINES.iter() {
+                let cell = self.cells[line[0]];
+                if line.iter().all(|&i| self.cells[i] == cell) {
+                    match cell {
+                        Cell::Black => return EndState::BlackWon,
+                        Cell::White => return EndState::WhiteWon,
+                        _ => {}
+                    }
+                }
+            }
+            if self.cells.iter().all(|cell| !cell.is_empty()) {
+                EndState::Tie
+            } else {
+                EndState::Unknown
+            }
+        }
+    }
+
+    #[test]
+    fn reversi_passes() {
+        let s = "WWWW
+                 WWWW
+                 BBWW
+                 B___";
+        let game = Reversi {
+            position: Position::parse(s).unwrap(),
+            variant: Variant::Normal,
+        };
+        // Black has no move but white has one, so black passes
+        assert!(game.position.allowed_moves().is_empty());
+        let moves = game.legal_moves();
+        assert_eq!(moves.len(), 1);
+        assert!(moves[0].is_none());
+        let mut next = game;
+        let pass = next.play(&moves[0]);
+        assert_eq!(next.to_move(), Cell::White);
+        assert!(!next.legal_moves().is_empty());
+        assert_eq!(game.result(), EndState::Unknown);
+        // Taking back white's move and the pass restores the position
+        let reply = next.legal_moves()[0].clone();
+        let undo = next.play(&reply);
+        assert!(next.position.board != game.position.board);
+        next.undo(undo);
+        next.undo(pass);
+        assert!(next.position.board == game.position.board);
+        assert_eq!(next.to_move(), Cell::Black);
+    }
+
+    #[test]
+    fn playouts_finish() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let reversi = Reversi {
+            position: Position::initial(6, &[]).unwrap(),
+            variant: Variant::Anti,
+        };
+        for _ in 0..10 {
+            assert!(random_playout(reversi, &mut rng).is_over());
+            let game = TicTacToe::parse("_________");
+            assert!(random_playout(game, &mut rng).is_over());
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use super::{report::RootMoveStats, *};
+use rand::Rng;
+
+/// Strength tier picked with `--level`, each knob weakens the bot
+/// the same way whatever the implementation
+#[derive(Clone, Copy, Debug)]
+pub struct Level {
+    /// Search depth of minimax
+    pub max_depth: usize,
+    /// Part of the time limit and of the playout budget the MCTS bots
+    /// use; minimax stops at its depth
+    pub budget_share: f64,
+    /// Temperature of the softmax over root move scores,
+    /// 0 always plays the best move
+    pub temperature: f64,
+    /// Largest random error added to minimax evaluations
+    pub eval_noise: Score,
+}
+
+/// From the weakest to the full strength
+pub const LEVELS: [Level; 5] = [
+    Level {
+        max_depth: 1,
+        budget_share: 0.05,
+        temperature: 0.5,
+        eval_noise: 400,
+    },
+    Level {
+        max_depth: 2,
+        budget_share: 0.1,
+        temperature: 0.25,
+        eval_noise: 200,
+    },
+    Level {
+        max_depth: 3,
+        budget_share: 0.25,
+        temperature: 0.1,
+        eval_noise: 50,
+    },
+    Level {
+        max_depth: 4,
+        budget_share: 0.5,
+        temperature: 0.03,
+        eval_noise: 0,
+    },
+    Level {
+        max_depth: 5,
+        budget_share: 1.0,
+        temperature: 0.0,
+        eval_noise: 0,
+    },
+];
+
+impl Level {
+    pub fn from_args(matches: &ArgMatches) -> Option<Self> {
+        matches
+            .value_of("level")
+            .map(|s| LEVELS[s.parse::<usize>().unwrap() - 1])
+    }
+
+    /// Picks a root move with probability growing with its score; scores
+    /// are scaled to their spread so minimax and MCTS behave alike
+    pub fn choose(
+        &self,
+        root_moves: &[RootMoveStats],
+        rng: &mut impl Rng,
+    ) -> Option<Point> {
+        let scored = root_moves
+            .iter()
+            .filter(|m| !m.score.is_nan())
+            .collect::<Vec<_>>();
+        let max = scored.iter().map(|m| m.score).fold(f64::MIN, f64::max);
+        let min = scored.iter().map(|m| m.score).fold(f64::MAX, f64::min);
+        if scored.is_empty() || self.temperature == 0.0 || max == min {
+            return None;
+        }
+        let weights = scored
+            .iter()
+            .map(|m| ((m.score - max) / (max - min) / self.temperature).exp())
+            .collect::<Vec<_>>();
+        let mut pick = rng.gen_range(0.0, weights.iter().sum::<f64>());
+        for (m, w) in scored.iter().zip(weights) {
+            if pick < w {
+                return Some(m.point);
+            }
+            pick -= w;
+        }
+        scored.last().map(|m| m.point)
+    }
+
+    /// Swaps the searched move for the chosen one; the report then only
+    /// knows the chosen move's score, the searched line followed another
+    pub fn apply(
+        &self,
+        result: (PlayerMove, report::SearchReport),
+        allowed_moves: &AllowedMoves,
+    ) -> (PlayerMove, report::SearchReport) {
+        let (pl_move, report) = result;
+        let chosen = self
+            .choose(&report.root_moves, &mut rng::thread_rng())
+            .and_then(|p| allowed_moves.iter().find(|m| m.0 == p));
+        match chosen {
+            Some(chosen) if chosen.0 != pl_move.0 => {
+                debug!(Search, "level plays {}", chosen.0.to_ab());
+                let mut report = report;
+                report.best_score = report
+                    .root_moves
+                    .iter()
+                    .find(|m| m.point == chosen.0)
+                    .map_or(f64::NAN, |m| m.score);
+                report.pv = Variation(vec![(chosen.0, report.best_score)]);
+                (chosen.clone(), report)
+            }
+            _ => (pl_move, report),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arena::play_game,
+        utils::{
+            config::BotConfig, position::Position, report::SearchReport,
+            variant::Variant,
+        },
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn stats(x: TileIdx, score: f64) -> RootMoveStats {
+        RootMoveStats {
+            point: Point::from_xy(x, 0),
+            visits: 1,
+            wins: 0,
+            score,
+        }
+    }
+
+    #[test]
+    fn choose_by_temperature() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let moves = [stats(0, 0.9), stats(1, 0.1), stats(2, f64::NAN)];
+        // Full strength keeps the searched move
+        assert!(LEVELS[4].choose(&moves, &mut rng).is_none());
+        let mut picks = [0; 3];
+        for _ in 0..1000 {
+            let p = LEVELS[0].choose(&moves, &mut rng).unwrap();
+            picks[p.to_xy().0 as usize] += 1;
+        }
+        // Unscored moves are never played, worse ones now and then
+        assert_eq!(picks[2], 0);
+        assert!(picks[0] > picks[1] && picks[1] > 0);
+    }
+
+    #[test]
+    fn apply_rebuilds_report() {
+        let mut report = SearchReport::new(Cell::Black);
+        report.root_moves = vec![stats(0, 0.9), stats(1, 0.1)];
+        let best = Point::from_xy(0, 0);
+        report.pv = Variation(vec![(best, 0.9), (Point::from_xy(5, 5), 0.9)]);
+        let allowed =
+            vec![(best, Vec::new()), (Point::from_xy(1, 0), Vec::new())];
+        for _ in 0..100 {
+            let result = ((best, Vec::new()), report.clone());
+            let (pl_move, report) = LEVELS[0].apply(result, &allowed);
+            if pl_move.0 != best {
+                assert_eq!(report.best_move(), Some(pl_move.0));
+                assert_eq!(report.best_score, 0.1);
+                assert!(report.pv.expected_reply().is_none());
+                return;
+            }
+        }
+        panic!("level 1 always played the best move");
+    }
+
+    /// Wins of the weaker and the stronger bot of `engine` over `games`
+    /// games on 6x6. Every pair of games starts from the same random
+    /// opening with colors swapped
+    fn play_levels(
+        engine: &str,
+        weak: usize,
+        strong: usize,
+        games: usize,
+    ) -> (usize, usize) {
+        let config = |level: usize| {
+            let level = level.to_string();
+            let args = [
+                "reversi_bot",
+                "--board-size",
+                "6",
+                "--no-anti",
+                "-t",
+                "0",
+                "--seed",
+                "1",
+                "--level",
+                &level,
+            ];
+            let mut args = args.to_vec();
+            args.extend(engine.split_whitespace());
+            let matches = app().get_matches_from(args);
+            // Noise, temperature and playouts draw from the seed
+            rng::seed_from_args(&matches);
+            BotConfig::from_args(&matches).unwrap()
+        };
+        let (weak, strong) = (config(weak), config(strong));
+        let mut openings = StdRng::seed_from_u64(1);
+        let mut position = Position::initial(6, &[]).unwrap();
+        let mut wins = (0, 0);
+        for game in 0..games {
+            if game % 2 == 0 {
+                position = Position::initial(6, &[]).unwrap();
+                position.play_random(4, &mut openings);
+            }
+            let (black, white, weak_color) = if game % 2 == 0 {
+                (&weak, &strong, Cell::Black)
+            } else {
+                (&strong, &weak, Cell::White)
+            };
+            let result = rng::tests::repeatable(|| {
+                let bots = [
+                    create_bot(black, position, Cell::Black),
+                    create_bot(white, position, Cell::White),
+                ];
+                play_game(bots, position, Variant::Normal)
+            });
+            if result.won(weak_color) {
+                wins.0 += 1;
+            } else if result.won(!weak_color) {
+                wins.1 += 1;
+            }
+        }
+        wins
+    }
+
+    #[test]
+    fn levels_ordered() {
+        let engines = ["--bot-impl minimax", "--bot-impl mcts --playouts 400"];
+        for engine in engines {
+            for weak in 1..LEVELS.len() {
+                let (weak_wins, strong_wins) =
+                    play_levels(engine, weak, weak + 1, 20);
+                assert!(
+                    strong_wins > weak_wins,
+                    "{}: level {} won {}, level {} won {}",
+                    engine,
+                    weak,
+                    weak_wins,
+                    weak + 1,
+                    strong_wins
+                );
+            }
+        }
+    }
+}
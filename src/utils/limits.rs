@@ -0,0 +1,144 @@
+use super::level::Level;
+use clap::ArgMatches;
+use crossbeam::channel::{
+    bounded, unbounded, Receiver, RecvTimeoutError, Sender,
+};
+use std::{
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// When a search stops: at the time limit, after the playout budget or
+/// at whichever comes first. Minimax counts its nodes as playouts
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub time: Option<Duration>,
+    pub playouts: Option<u64>,
+}
+
+impl Limits {
+    /// `--time-limit 0` leaves only the playout budget. A level takes
+    /// its share of both
+    pub fn from_args(matches: &ArgMatches, level: Option<Level>) -> Self {
+        let share = level.map_or(1.0, |level| level.budget_share);
+        let millis = matches
+            .value_of("time_limit")
+            .map(|it| it.parse::<u64>().unwrap())
+            .unwrap();
+        let millis = (millis as f64 * share) as u64;
+        let playouts = matches
+            .value_of("playouts")
+            .map(|it| it.parse::<u64>().unwrap())
+            .map(|n| ((n as f64 * share) as u64).max(1));
+        let time = Some(Duration::from_millis(millis)).filter(|_| millis > 0);
+        Self { time, playouts }
+    }
+
+    /// Refuses limits that would never stop an MCTS search
+    pub fn check(&self) -> Result<(), String> {
+        if self.time.is_none() && self.playouts.is_none() {
+            let e = "Search needs a time limit or a playout budget";
+            return Err(e.to_string());
+        }
+        Ok(())
+    }
+
+    /// Budget of one of `rounds` more searches after `elapsed`: an even
+    /// share of the time left, and the playouts of one of the `nmoves`
+    /// root moves
+    pub fn extension(
+        &self,
+        nmoves: usize,
+        elapsed: Duration,
+        rounds: usize,
+    ) -> Self {
+        Self {
+            time: self
+                .time
+                .map(|time| time.saturating_sub(elapsed) / rounds as u32),
+            playouts: self.playouts.map(|n| n / nmoves as u64),
+        }
+    }
+
+    /// Playouts for root move `index` of `nmoves`, the budget is split
+    /// evenly and the first moves get the remainder
+    pub fn playouts_for(&self, index: usize, nmoves: usize) -> u64 {
+        match self.playouts {
+            Some(playouts) => {
+                let nmoves = nmoves as u64;
+                playouts / nmoves + ((index as u64) < playouts % nmoves) as u64
+            }
+            None => u64::MAX,
+        }
+    }
+}
+
+/// Sends one stop signal per worker once the time is up, unless
+/// `finish` is called earlier
+pub struct Timer {
+    stop_rx: Receiver<()>,
+    done_tx: Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Timer {
+    pub fn start(time: Option<Duration>, nworkers: usize) -> Self {
+        let (stop_tx, stop_rx) = unbounded::<()>();
+        let (done_tx, done_rx) = bounded::<()>(1);
+        let handle = time.map(|time| {
+            thread::spawn(move || {
+                if let Err(RecvTimeoutError::Timeout) =
+                    done_rx.recv_timeout(time)
+                {
+                    for _ in 0..nworkers {
+                        stop_tx.send(()).ok();
+                    }
+                }
+            })
+        });
+        Self {
+            stop_rx,
+            done_tx,
+            handle,
+        }
+    }
+
+    /// Whether the calling worker has to stop, takes its stop signal
+    pub fn is_over(&self) -> bool {
+        self.stop_rx.try_recv().is_ok()
+    }
+
+    pub fn finish(self) {
+        self.done_tx.send(()).ok();
+        if let Some(handle) = self.handle {
+            handle.join().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_playouts() {
+        let limits = Limits {
+            time: None,
+            playouts: Some(10),
+        };
+        let shares = (0..4).map(|i| limits.playouts_for(i, 4));
+        assert_eq!(shares.collect::<Vec<_>>(), vec![3, 3, 2, 2]);
+    }
+
+    #[test]
+    fn timer_stops_workers() {
+        let timer = Timer::start(Some(Duration::from_millis(1)), 2);
+        thread::sleep(Duration::from_millis(20));
+        assert!(timer.is_over() && timer.is_over() && !timer.is_over());
+        timer.finish();
+        // Finishing early doesn't wait for the time limit
+        let timer = Timer::start(Some(Duration::from_secs(60)), 1);
+        assert!(!timer.is_over());
+        timer.finish();
+    }
+}
@@ -0,0 +1,224 @@
+use clap::ArgMatches;
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{stderr, BufWriter, Write},
+    sync::{Mutex, OnceLock},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Error,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "error" => Some(Level::Error),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subsystem {
+    /// Search setup, reports and predictions
+    Search,
+    /// Everything read from or sent to the opponent
+    Protocol,
+    /// Time spent per move
+    Time,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 3] =
+        [Subsystem::Search, Subsystem::Protocol, Subsystem::Time];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Subsystem::ALL.iter().cloned().find(|sub| sub.name() == s)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Subsystem::Search => "search",
+            Subsystem::Protocol => "protocol",
+            Subsystem::Time => "time",
+        }
+    }
+}
+
+type Sink = Mutex<BufWriter<File>>;
+
+pub struct Logger {
+    level: Level,
+    subsystems: Vec<Subsystem>,
+    file: Option<Sink>,
+    stderr: bool,
+    json: Option<Sink>,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+impl Logger {
+    pub fn from_args(matches: &ArgMatches) -> Self {
+        let level = matches
+            .value_of("log_level")
+            .and_then(Level::parse)
+            .unwrap_or(Level::Info);
+        let subsystems = match matches.values_of("log_filter") {
+            Some(names) => names.filter_map(Subsystem::parse).collect(),
+            None => Subsystem::ALL.to_vec(),
+        };
+        Self {
+            level,
+            subsystems,
+            file: matches.value_of("log_file").map(open_sink),
+            stderr: matches.is_present("log_stderr"),
+            json: matches.value_of("log_json").map(open_sink),
+        }
+    }
+
+    /// Installs the logger for the rest of the process;
+    /// messages are dropped until this is called
+    pub fn init(self) {
+        LOGGER.set(self).ok();
+    }
+
+    fn enabled(&self, level: Level, subsystem: Subsystem) -> bool {
+        (self.file.is_some() || self.stderr)
+            && level <= self.level
+            && self.subsystems.contains(&subsystem)
+    }
+}
+
+fn open_sink(name: &str) -> Sink {
+    OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(name)
+        .map(|f| Mutex::new(BufWriter::new(f)))
+        .expect("Opened file for logging")
+}
+
+pub fn enabled(level: Level, subsystem: Subsystem) -> bool {
+    LOGGER
+        .get()
+        .is_some_and(|logger| logger.enabled(level, subsystem))
+}
+
+pub fn write(level: Level, subsystem: Subsystem, args: fmt::Arguments) {
+    let logger = match LOGGER.get() {
+        Some(logger) if logger.enabled(level, subsystem) => logger,
+        _ => return,
+    };
+    let line = format!("[{} {}] {}\n", level.name(), subsystem.name(), args);
+    if let Some(file) = &logger.file {
+        file.lock().unwrap().write_all(line.as_bytes()).ok();
+    }
+    if logger.stderr {
+        stderr().write_all(line.as_bytes()).ok();
+    }
+}
+
+/// Whether a JSON-lines sink was requested
+pub fn json_enabled() -> bool {
+    LOGGER.get().is_some_and(|logger| logger.json.is_some())
+}
+
+pub fn write_json(line: &str) {
+    if let Some(json) = LOGGER.get().and_then(|logger| logger.json.as_ref()) {
+        writeln!(json.lock().unwrap(), "{}", line).ok();
+    }
+}
+
+pub fn flush() {
+    if let Some(logger) = LOGGER.get() {
+        for sink in logger.file.iter().chain(logger.json.iter()) {
+            sink.lock().unwrap().flush().ok();
+        }
+    }
+}
+
+macro_rules! log_at {
+    ($level:ident, $sub:ident, $($fmtargs:expr),+ $(,)*) => {
+        if $crate::utils::logger::enabled(
+            $crate::utils::logger::Level::$level,
+            $crate::utils::logger::Subsystem::$sub,
+        ) {
+            $crate::utils::logger::write(
+                $crate::utils::logger::Level::$level,
+                $crate::utils::logger::Subsystem::$sub,
+                format_args!($($fmtargs),+),
+            );
+        }
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! error {
+    ($sub:ident, $($fmtargs:expr),+ $(,)*) => {
+        log_at!(Error, $sub, $($fmtargs),+)
+    };
+}
+
+macro_rules! info {
+    ($sub:ident, $($fmtargs:expr),+ $(,)*) => {
+        log_at!(Info, $sub, $($fmtargs),+)
+    };
+}
+
+macro_rules! debug {
+    ($sub:ident, $($fmtargs:expr),+ $(,)*) => {
+        log_at!(Debug, $sub, $($fmtargs),+)
+    };
+}
+
+#[allow(unused_macros)]
+macro_rules! trace {
+    ($sub:ident, $($fmtargs:expr),+ $(,)*) => {
+        log_at!(Trace, $sub, $($fmtargs),+)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_ordering() {
+        assert!(Level::Error < Level::Info);
+        assert!(Level::Debug < Level::Trace);
+        assert_eq!(Level::parse("debug"), Some(Level::Debug));
+        assert_eq!(Level::parse("verbose"), None);
+    }
+
+    #[test]
+    fn subsystem_filter() {
+        let logger = Logger {
+            level: Level::Debug,
+            subsystems: vec![Subsystem::Search],
+            file: None,
+            stderr: true,
+            json: None,
+        };
+        assert!(logger.enabled(Level::Info, Subsystem::Search));
+        assert!(!logger.enabled(Level::Trace, Subsystem::Search));
+        assert!(!logger.enabled(Level::Info, Subsystem::Protocol));
+    }
+}
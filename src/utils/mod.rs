@@ -1,31 +1,40 @@
+#[macro_use]
+pub mod logger;
 pub mod board;
+pub mod config;
+pub mod elo;
+pub mod final_move;
+pub mod game;
+pub mod level;
+pub mod limits;
+pub mod negamax;
 pub mod point;
+pub mod position;
+pub mod report;
+pub mod rng;
+pub mod rollout;
 pub mod sev;
 pub mod tree;
 pub mod tree2;
+pub mod variant;
+pub mod zobrist;
 
 use clap::{App, AppSettings, Arg, ArgMatches};
 use point::Point;
 use std::{
-    fs::{File, OpenOptions},
-    io::{stdin, stdout, BufWriter, Write},
+    fmt,
+    io::{stdin, stdout, Write},
     process,
-    sync::{Arc, Mutex},
 };
 
-use crate::bot::Bot;
+use crate::{bot::Bot, search::Engine};
 
-use self::board::Board;
-
-// A small logging util
-macro_rules! log {
-    ($slf:ident, $($fmtargs:expr),+ $(,)*) => {
-        if let Some(log_file) = &$slf.get_logfile() {
-            let mut lck = log_file.lock().unwrap();
-            writeln!(lck, $($fmtargs),+).unwrap();
-        }
-    }
-}
+use self::{
+    board::{Board, DEFAULT_SIZE},
+    config::BotConfig,
+    position::Position,
+    variant::Variant,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Cell {
@@ -47,18 +56,12 @@ impl Cell {
     #[inline]
     #[allow(dead_code)]
     pub fn is_disc(&self) -> bool {
-        match self {
-            Cell::White | Cell::Black => true,
-            _ => false,
-        }
+        matches!(self, Cell::White | Cell::Black)
     }
 
     #[inline]
     pub fn is_empty(&self) -> bool {
-        match self {
-            Cell::Empty => true,
-            _ => false,
-        }
+        matches!(self, Cell::Empty)
     }
 }
 
@@ -80,10 +83,7 @@ pub enum EndState {
 
 impl EndState {
     pub fn is_over(&self) -> bool {
-        match self {
-            EndState::Unknown => false,
-            _ => true,
-        }
+        !matches!(self, EndState::Unknown)
     }
 
     pub fn won(&self, cell: Cell) -> bool {
@@ -95,13 +95,76 @@ impl EndState {
     }
 }
 
+/// End of a playout with the final disc difference
+#[derive(Clone, Copy, Debug)]
+pub struct Outcome {
+    pub result: EndState,
+    /// Black minus white discs over all discs, from -1 to 1
+    pub disc_share: f64,
+}
+
+impl Outcome {
+    pub fn new(board: &Board, result: EndState) -> Self {
+        let nblack = board.count(Cell::Black) as f64;
+        let nwhite = board.count(Cell::White) as f64;
+        let disc_share = (nblack - nwhite) / (nblack + nwhite).max(1.0);
+        Self { result, disc_share }
+    }
+
+    /// What the game was worth to `color`, from 0 to 1: a tie is half
+    /// a win and `margin_weight` of the reward comes from the disc margin
+    pub fn reward(
+        &self,
+        color: Cell,
+        variant: Variant,
+        margin_weight: f64,
+    ) -> f64 {
+        let result = variant::result_value(self.result, color);
+        let mut margin = self.disc_share * variant.sign() as f64;
+        if color == Cell::White {
+            margin = -margin;
+        }
+        (1.0 - margin_weight) * result + margin_weight * (1.0 + margin) / 2.0
+    }
+}
+
 pub enum CLIMove {
     Coord(Point),
     Color(Cell),
     Pass,
+    /// Start of the next game in a session: our color and the black holes
+    NewGame(Cell, Vec<Point>),
 }
 
 impl CLIMove {
+    /// Parses a protocol line: a coordinate, `pass`, a color, or `newgame`
+    /// followed by our color and the black holes in any order
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut tokens = s.split_whitespace();
+        let first = tokens.next()?;
+        if first == "newgame" {
+            let mut color = None;
+            let mut black_holes = Vec::new();
+            for token in tokens {
+                match token {
+                    "black" => color = Some(Cell::Black),
+                    "white" => color = Some(Cell::White),
+                    _ => black_holes.push(Point::from_ab(token)?),
+                }
+            }
+            return Some(CLIMove::NewGame(color?, black_holes));
+        }
+        if tokens.next().is_some() {
+            return None;
+        }
+        match first {
+            "pass" => Some(CLIMove::Pass),
+            "black" => Some(CLIMove::Color(Cell::Black)),
+            "white" => Some(CLIMove::Color(Cell::White)),
+            _ => Point::from_ab(first).map(CLIMove::Coord),
+        }
+    }
+
     pub fn coord(self) -> Point {
         if let CLIMove::Coord(p) = self {
             p
@@ -124,6 +187,35 @@ pub type AllowedMoves = Vec<PlayerMove>;
 pub type Score = i32;
 pub type AlphaBeta = (Score, Score);
 
+/// Principal variation: the line of play the search expects,
+/// each move paired with the score the search assigned to it
+#[derive(Clone, Default)]
+pub struct Variation(pub Vec<(Point, f64)>);
+
+impl Variation {
+    /// The opponent's reply the search was expecting
+    pub fn expected_reply(&self) -> Option<Point> {
+        self.0.get(1).map(|(p, _)| *p)
+    }
+}
+
+impl fmt::Display for Variation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let moves = self
+            .0
+            .iter()
+            .map(|(p, score)| {
+                if score.is_nan() {
+                    p.to_ab()
+                } else {
+                    format!("{} ({:.3})", p.to_ab(), score)
+                }
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", moves.join(" "))
+    }
+}
+
 pub fn input() -> String {
     loop {
         let mut sbuf = String::new();
@@ -133,7 +225,7 @@ pub fn input() -> String {
                 process::exit(0);
             }
             let result = sbuf.trim().to_string();
-            if result.len() > 0 {
+            if !result.is_empty() {
                 return result;
             }
         } else {
@@ -147,18 +239,27 @@ pub struct Chan {}
 impl Chan {
     pub fn read() -> CLIMove {
         let s = input();
-        match s.as_str() {
-            "pass" => CLIMove::Pass,
-            "black" => CLIMove::Color(Cell::Black),
-            "white" => CLIMove::Color(Cell::White),
-            _ => {
-                if let Some(ab) = Point::from_ab(&s) {
-                    CLIMove::Coord(ab)
-                } else {
-                    panic!("Unexpected command");
-                }
-            }
-        }
+        debug!(Protocol, "<- {}", s);
+        CLIMove::parse(&s).unwrap_or_else(|| {
+            error!(Protocol, "unexpected command: {}", s);
+            logger::flush();
+            panic!("Unexpected command");
+        })
+    }
+
+    /// Reads a line of whitespace separated coordinates
+    pub fn read_coords() -> Vec<Point> {
+        let s = input();
+        debug!(Protocol, "<- {}", s);
+        s.split_whitespace()
+            .map(|ab| {
+                Point::from_ab(ab).unwrap_or_else(|| {
+                    error!(Protocol, "unexpected coordinate: {}", ab);
+                    logger::flush();
+                    panic!("Expected coordinate")
+                })
+            })
+            .collect()
     }
 
     pub fn send(p: CLIMove) {
@@ -167,6 +268,7 @@ impl Chan {
             CLIMove::Coord(p) => p.to_ab(),
             _ => panic!("Unexpected command"),
         };
+        debug!(Protocol, "-> {}", line);
         stdout()
             .write_all(format!("{}\n", line).as_bytes())
             .unwrap();
@@ -184,27 +286,31 @@ pub const TRAVERSE_DIRECTIONS: [(TileIdx, TileIdx); 8] = [
     (-1, -1),
 ];
 
-pub fn repr_board(board: &[Cell]) -> String {
-    let mut col = 0;
-    let mut cells: Vec<Vec<&str>> = Vec::with_capacity(8);
-    cells.push(Vec::with_capacity(8));
-    for cell in board.iter() {
-        let cell_repr = match cell {
+pub fn repr_board(board: &Board) -> String {
+    repr_board_with_moves(board, &[])
+}
+
+/// Same as `repr_board` with the given empty tiles marked as moves
+pub fn repr_board_with_moves(board: &Board, moves: &[Point]) -> String {
+    let size = board.size as usize;
+    let mut cells: Vec<Vec<&str>> = Vec::with_capacity(size);
+    for p in board.points() {
+        if p.to_xy().0 == 0 {
+            cells.push(Vec::with_capacity(size));
+        }
+        let cell_repr = match board.at(p) {
             Cell::White => "██",
             Cell::Black => "░░",
             Cell::BlackHole => "BH",
+            Cell::Empty if moves.contains(&p) => "<>",
             Cell::Empty => "▒▒",
         };
         cells.last_mut().unwrap().push(cell_repr);
-        col += 1;
-        if col == 8 && cells.len() < 8 {
-            cells.push(Vec::with_capacity(8));
-            col = 0;
-        }
     }
     let letters = format!(
         "  {}",
-        ('A'..='H')
+        ('A'..)
+            .take(size)
             .map(|c| format!(" {}", c))
             .collect::<Vec<String>>()
             .join("")
@@ -212,7 +318,7 @@ pub fn repr_board(board: &[Cell]) -> String {
     let brd = cells
         .iter()
         .enumerate()
-        .map(|(index, row)| format!(" {}{}", index + 1, row.join("")))
+        .map(|(index, row)| format!("{:>2}{}", index + 1, row.join("")))
         .collect::<Vec<String>>()
         .join("\n");
     format!("{}\n{}\n", letters, brd)
@@ -237,23 +343,35 @@ pub fn min_of(s1: Score, s2: Score) -> Score {
 
 #[inline]
 pub fn get_allowed_moves(board: &Board, color: Cell) -> AllowedMoves {
+    // Same code, but 8x8 gets a copy with the size known when compiling
+    if board.size == DEFAULT_SIZE {
+        allowed_moves_sized(board, color, DEFAULT_SIZE)
+    } else {
+        allowed_moves_sized(board, color, board.size)
+    }
+}
+
+#[inline(always)]
+fn allowed_moves_sized(
+    board: &Board,
+    color: Cell,
+    size: TileIdx,
+) -> AllowedMoves {
     let rev_color = color.opposite();
-    let only_current_tiles = board
-        .0
-        .iter()
-        .enumerate()
-        .filter(|(_, &cell)| cell == color)
+    let only_current_tiles = (0..size)
+        .flat_map(|y| (0..size).map(move |x| Point::from_xy(x, y)))
+        .filter(|&p| board.at(p) == color)
         .collect::<Vec<_>>();
     let mut res: AllowedMoves = Vec::with_capacity(only_current_tiles.len());
 
-    for (index, _) in only_current_tiles {
-        let start_point = Point::from_idx(index as TileIdx);
+    for start_point in only_current_tiles {
         let (x, y) = start_point.to_xy();
 
         for (dx, dy) in TRAVERSE_DIRECTIONS.iter() {
             let (mut x, mut y) = (x + dx, y + dy);
-            let mut to_be_flipped: Vec<Point> = Vec::with_capacity(7);
-            let range = 0..8;
+            let mut to_be_flipped: Vec<Point> =
+                Vec::with_capacity(size as usize - 1);
+            let range = 0..size;
 
             while range.contains(&x) && range.contains(&y) {
                 let tile_pt = Point::from_xy(x, y);
@@ -261,7 +379,7 @@ pub fn get_allowed_moves(board: &Board, color: Cell) -> AllowedMoves {
                 if tile == rev_color {
                     to_be_flipped.push(tile_pt);
                 } else {
-                    if tile == Cell::Empty && to_be_flipped.len() > 0 {
+                    if tile == Cell::Empty && !to_be_flipped.is_empty() {
                         let old_row =
                             res.iter_mut().find(|(t_i, _)| *t_i == tile_pt);
                         if let Some(p_move) = old_row {
@@ -282,6 +400,11 @@ pub fn get_allowed_moves(board: &Board, color: Cell) -> AllowedMoves {
 }
 
 pub fn parse_args() -> ArgMatches<'static> {
+    app().get_matches()
+}
+
+/// Command line of the bot, also used to parse bot specs in the arena
+pub fn app() -> App<'static, 'static> {
     App::new(env!("CARGO_PKG_NAME"))
         .setting(AppSettings::DisableVersion)
         .arg(
@@ -303,13 +426,51 @@ pub fn parse_args() -> ArgMatches<'static> {
                 .long("log")
                 .env("LOG")
                 .takes_value(true)
+                .global(true)
                 .help("File for logging"),
         )
+        .arg(
+            Arg::with_name("log_stderr")
+                .long("log-stderr")
+                .env("LOG_STDERR")
+                .takes_value(false)
+                .global(true)
+                .help("Also write log messages to stderr"),
+        )
+        .arg(
+            Arg::with_name("log_level")
+                .long("log-level")
+                .env("LOG_LEVEL")
+                .takes_value(true)
+                .possible_values(&["error", "info", "debug", "trace"])
+                .default_value("info")
+                .global(true)
+                .help("Most verbose level of messages to log"),
+        )
+        .arg(
+            Arg::with_name("log_filter")
+                .long("log-filter")
+                .env("LOG_FILTER")
+                .takes_value(true)
+                .use_delimiter(true)
+                .possible_values(&["search", "protocol", "time"])
+                .global(true)
+                .help("Only log messages of these subsystems"),
+        )
+        .arg(
+            Arg::with_name("log_json")
+                .long("log-json")
+                .env("LOG_JSON")
+                .takes_value(true)
+                .global(true)
+                .help("File for search reports, one JSON object per line"),
+        )
         .arg(
             Arg::with_name("no_anti")
                 .long("no-anti")
                 .env("NO_ANTI")
                 .takes_value(false)
+                .global(true)
                 .help("Play regular reversi"),
         )
         .arg(
@@ -317,7 +478,17 @@ pub fn parse_args() -> ArgMatches<'static> {
                 .long("no-blackhole")
                 .env("NO_BLACKHOLE")
                 .takes_value(false)
-                .help("Disable BlackHole mode"),
+                .help("Disable BlackHole mode (no black holes line on stdin)"),
+        )
+        .arg(
+            Arg::with_name("board_size")
+                .long("board-size")
+                .takes_value(true)
+                .env("BOARD_SIZE")
+                .possible_values(&["4", "6", "8", "10"])
+                .default_value("8")
+                .global(true)
+                .help("Side of the board"),
         )
         .arg(
             Arg::with_name("time_limit")
@@ -326,7 +497,14 @@ pub fn parse_args() -> ArgMatches<'static> {
                 .takes_value(true)
                 .env("MAX_TIME")
                 .default_value("4950")
-                .help("Set time limit in milliseconds (for MCTS)"),
+                .help("Set time limit in milliseconds (for MCTS), 0 for none"),
+        )
+        .arg(
+            Arg::with_name("playouts")
+                .long("playouts")
+                .takes_value(true)
+                .env("PLAYOUTS")
+                .help("Playouts per move (for MCTS), split among root moves"),
         )
         .arg(
             Arg::with_name("bot_impl")
@@ -337,82 +515,180 @@ pub fn parse_args() -> ArgMatches<'static> {
                     "mcts_basic",
                     "mcts",
                     "mcts_minimax",
+                    "mcts_rave",
                 ])
                 .env("BOT_IMPL")
                 .default_value("mcts"),
         )
+        .arg(
+            Arg::with_name("rave_k")
+                .long("rave-k")
+                .takes_value(true)
+                .env("RAVE_K")
+                .default_value("500")
+                .help(
+                    "Visits at which RAVE and UCT weigh the same (mcts_rave)",
+                ),
+        )
+        .arg(
+            Arg::with_name("prior_bias")
+                .long("prior-bias")
+                .takes_value(true)
+                .env("PRIOR_BIAS")
+                .default_value("0")
+                .help("Prior weight in mcts_minimax and mcts_rave selection"),
+        )
+        .arg(
+            Arg::with_name("widening")
+                .long("widening")
+                .help("Open tree children in order of prior, same bots"),
+        )
+        .arg(
+            Arg::with_name("margin_weight")
+                .long("margin-weight")
+                .takes_value(true)
+                .env("MARGIN_WEIGHT")
+                .default_value("0")
+                .help("Share of the disc margin in MCTS tree playout rewards"),
+        )
+        .arg(
+            Arg::with_name("minimax_weight")
+                .long("minimax-weight")
+                .takes_value(true)
+                .env("MINIMAX_WEIGHT")
+                .default_value("0.3")
+                .help("Share of minimax values in mcts_minimax node scores"),
+        )
+        .arg(
+            Arg::with_name("minimax_threshold")
+                .long("minimax-threshold")
+                .takes_value(true)
+                .env("MINIMAX_THRESHOLD")
+                .default_value("5")
+                .help("Visits before mcts_minimax searches a node's children"),
+        )
+        .arg(
+            Arg::with_name("final_move")
+                .long("final-move")
+                .takes_value(true)
+                .possible_values(&["robust", "ratio", "secure", "max-robust"])
+                .env("FINAL_MOVE")
+                .default_value("ratio")
+                .help("How MCTS bots pick their move from the root moves"),
+        )
+        .arg(
+            Arg::with_name("rollout")
+                .long("rollout")
+                .takes_value(true)
+                .possible_values(&[
+                    "random",
+                    "greedy",
+                    "epsilon-greedy",
+                    "softmax",
+                ])
+                .env("ROLLOUT")
+                .help("Playout policy of MCTS bots, each has its own default"),
+        )
+        .arg(
+            Arg::with_name("rollout_epsilon")
+                .long("rollout-epsilon")
+                .takes_value(true)
+                .default_value("0.1")
+                .help("Chance of a random move in epsilon-greedy playouts"),
+        )
+        .arg(
+            Arg::with_name("rollout_temperature")
+                .long("rollout-temperature")
+                .takes_value(true)
+                .default_value("1")
+                .help("Temperature of softmax playouts"),
+        )
+        .arg(
+            Arg::with_name("rollout_cutoff")
+                .long("rollout-cutoff")
+                .takes_value(true)
+                .help("Moves after which playouts stop and get evaluated"),
+        )
+        .arg(
+            Arg::with_name("level")
+                .long("level")
+                .takes_value(true)
+                .env("LEVEL")
+                .possible_values(&["1", "2", "3", "4", "5"])
+                .help("Strength from 1 (weakest) to 5, overrides --max-depth"),
+        )
         .arg(
             Arg::with_name("exploitation_value")
                 .long("mcts-exp")
                 .takes_value(true)
                 .env("EXP"),
         )
-        .get_matches()
-}
-
-pub type LogFile = Option<Arc<Mutex<BufWriter<File>>>>;
-
-pub fn get_logfile(matches: &ArgMatches) -> LogFile {
-    matches.value_of("log_file").map(|name| {
-        OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true)
-            .open(name)
-            .map(|f| Arc::new(Mutex::new(BufWriter::new(f))))
-            .expect("Opened file for logging")
-    })
+        .arg(
+            Arg::with_name("position")
+                .long("position")
+                .takes_value(true)
+                .global(true)
+                .help("Start from the board in this file"),
+        )
+        .arg(
+            Arg::with_name("moves")
+                .long("moves")
+                .takes_value(true)
+                .global(true)
+                .help("Moves played before the game starts, e.g. \"D3 C5\""),
+        )
+        .arg(
+            Arg::with_name("random_opening")
+                .long("random-opening")
+                .takes_value(true)
+                .global(true)
+                .help("Number of random moves played before the game starts"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .env("SEED")
+                .global(true)
+                .help("Seed for all randomness, single-threaded runs repeat"),
+        )
+        .subcommand(crate::arena::subcommand())
+        .subcommand(crate::nboard::subcommand())
+        .subcommand(crate::perft::subcommand())
+        .subcommand(crate::play::subcommand())
+        .subcommand(crate::session::subcommand())
+        .subcommand(crate::tournament::subcommand())
 }
 
 #[inline]
 pub fn wincheck(
     board: &Board,
     allowed_moves: &AllowedMoves,
-    is_anti: bool,
+    variant: Variant,
     color: Cell,
 ) -> EndState {
-    if allowed_moves.len() > 0 {
+    if !allowed_moves.is_empty() {
         return EndState::Unknown;
     }
     let maybepassmoves = board.allowed_moves(color.opposite());
-    if maybepassmoves.len() > 0 {
+    if !maybepassmoves.is_empty() {
         return EndState::Unknown;
     }
-
-    let mut nblack = 0;
-    let mut nwhite = 0;
-    for disc in board.0.iter() {
-        match disc {
-            Cell::White => nwhite += 1,
-            Cell::Black => nblack += 1,
-            _ => {}
-        };
-    }
-
-    if nblack > nwhite {
-        if is_anti {
-            EndState::WhiteWon
-        } else {
-            EndState::BlackWon
-        }
-    } else if nblack < nwhite {
-        if is_anti {
-            EndState::BlackWon
-        } else {
-            EndState::WhiteWon
-        }
-    } else {
-        EndState::Tie
-    }
+    variant.result(board)
 }
 
-pub fn uct_score(parent_nvisits: u64, nwins: u64, nvisits: u64, c: f64) -> f64 {
+pub fn uct_score(
+    parent_nvisits: u64,
+    reward: f64,
+    nvisits: u64,
+    c: f64,
+) -> f64 {
     if nvisits == 0 {
         f64::MAX
     } else {
         let nvisits = nvisits as f64;
         let parent_nvisits = parent_nvisits as f64;
-        let xi = nwins as f64 / nvisits;
+        let xi = reward / nvisits;
         xi + c * (parent_nvisits.ln() / nvisits).sqrt()
     }
 }
@@ -420,33 +696,91 @@ pub fn uct_score(parent_nvisits: u64, nwins: u64, nvisits: u64, c: f64) -> f64 {
 #[allow(non_snake_case)]
 pub fn get_LCB_UCB(
     parent_nvisits: u64,
-    nwins: u64,
+    reward: f64,
     nvisits: u64,
     exploration: f64,
 ) -> (f64, f64) {
-    let (nwins, nvisits, parent_nvisits) =
-        (nwins as f64, nvisits as f64, parent_nvisits as f64);
-    let mean = nwins / nvisits;
-    let exploration = exploration * (parent_nvisits.ln() / nvisits);
+    let (nvisits, parent_nvisits) = (nvisits as f64, parent_nvisits as f64);
+    let mean = reward / nvisits;
+    let exploration = exploration * (parent_nvisits.ln() / nvisits).sqrt();
     (mean - exploration, mean + exploration)
 }
 
+/// Reads the black holes and our color from stdin and creates the bot
 pub fn select_bot_impl(matches: &ArgMatches) -> Box<dyn Bot> {
-    use crate::{mcts, mcts2, mcts3, minimax};
-    match matches.value_of("bot_impl").unwrap() {
-        "minimax" => Box::new(minimax::MinimaxBot::new(matches)),
-        "mcts_basic" => Box::new(mcts::MCTSBot::new(matches)),
-        "mcts" => Box::new(mcts2::MCTSBot::new(matches)),
-        "mcts_minimax" => Box::new(mcts3::MCTSMinimaxBot::new(matches)),
-        _ => unreachable!(),
+    let black_holes = read_black_holes(matches);
+    let my_color = Chan::read().color();
+    info!(
+        Protocol,
+        "black holes: {:?}",
+        black_holes.iter().map(|p| p.to_ab()).collect::<Vec<_>>()
+    );
+    info!(Protocol, "my color: {:?}", my_color);
+
+    let position = match Position::from_args(matches, &black_holes) {
+        Ok(position) => position,
+        Err(e) => {
+            error!(Protocol, "bad starting position: {}", e);
+            logger::flush();
+            panic!("Bad starting position: {}", e);
+        }
+    };
+    let config = match BotConfig::from_args(matches) {
+        Ok(config) => config,
+        Err(e) => {
+            error!(Search, "bad bot settings: {}", e);
+            logger::flush();
+            panic!("Bad bot settings: {}", e);
+        }
+    };
+    create_bot(&config, position, my_color)
+}
+
+pub fn create_bot(
+    config: &BotConfig,
+    position: Position,
+    my_color: Cell,
+) -> Box<dyn Bot> {
+    use crate::{mcts, mcts2, mcts3, mcts4, minimax};
+    match config.engine {
+        Engine::Minimax => {
+            Box::new(minimax::MinimaxBot::new(config, position, my_color))
+        }
+        Engine::MctsBasic => {
+            Box::new(mcts::MCTSBot::new(config, position, my_color))
+        }
+        Engine::Mcts => {
+            Box::new(mcts2::MCTSBot::new(config, position, my_color))
+        }
+        Engine::MctsMinimax => {
+            Box::new(mcts3::MCTSMinimaxBot::new(config, position, my_color))
+        }
+        Engine::MctsRave => {
+            Box::new(mcts4::MCTSRaveBot::new(config, position, my_color))
+        }
     }
 }
 
-pub fn read_black_hole(matches: &ArgMatches) -> Option<Point> {
+pub fn board_size(matches: &ArgMatches) -> TileIdx {
+    matches
+        .value_of("board_size")
+        .map(|s| s.parse::<TileIdx>().unwrap())
+        .unwrap()
+}
+
+/// Parses whitespace separated coordinates like `D3 F6`
+pub fn parse_coords(s: &str) -> Result<Vec<Point>, String> {
+    s.split_whitespace()
+        .map(|ab| Point::from_ab(ab).ok_or(format!("Bad coordinate: {}", ab)))
+        .collect()
+}
+
+/// Black holes are given on a single line, e.g. `D3` or `D3 F6 A1`
+pub fn read_black_holes(matches: &ArgMatches) -> Vec<Point> {
     if matches.is_present("no_blackhole") {
-        None
+        Vec::new()
     } else {
-        Some(Chan::read().coord())
+        Chan::read_coords()
     }
 }
 
@@ -467,7 +801,12 @@ mod utils_test {
              BBBBBBBB";
         let b = Board::try_from(s.to_string()).unwrap();
         assert_eq!(
-            wincheck(&b, &b.allowed_moves(Cell::White), true, Cell::Black),
+            wincheck(
+                &b,
+                &b.allowed_moves(Cell::White),
+                Variant::Anti,
+                Cell::Black
+            ),
             EndState::WhiteWon
         );
     }
@@ -483,8 +822,12 @@ mod utils_test {
              BBBBBBBB
              BBBBBBBB";
         let b = Board::try_from(s.to_string()).unwrap();
-        let win =
-            wincheck(&b, &b.allowed_moves(Cell::White), true, Cell::Black);
+        let win = wincheck(
+            &b,
+            &b.allowed_moves(Cell::White),
+            Variant::Anti,
+            Cell::Black,
+        );
         assert!(win.is_over());
         assert_eq!(win, EndState::WhiteWon);
     }
@@ -500,9 +843,70 @@ mod utils_test {
              WWWWWWWW
              WWWWWWWW";
         let b = Board::try_from(s.to_string()).unwrap();
-        let win =
-            wincheck(&b, &b.allowed_moves(Cell::White), true, Cell::Black);
+        let win = wincheck(
+            &b,
+            &b.allowed_moves(Cell::White),
+            Variant::Anti,
+            Cell::Black,
+        );
         assert!(win.is_over());
         assert_eq!(win, EndState::Tie);
     }
+
+    #[test]
+    fn protocol_lines() {
+        let d3 = Point::from_ab("D3").unwrap();
+        assert!(
+            matches!(CLIMove::parse("D3"), Some(CLIMove::Coord(p)) if p == d3)
+        );
+        assert!(matches!(CLIMove::parse("pass"), Some(CLIMove::Pass)));
+        assert!(matches!(
+            CLIMove::parse("white"),
+            Some(CLIMove::Color(Cell::White))
+        ));
+        match CLIMove::parse("newgame D3 black F6") {
+            Some(CLIMove::NewGame(Cell::Black, holes)) => {
+                assert_eq!(holes, vec![d3, Point::from_ab("F6").unwrap()])
+            }
+            _ => panic!("Expected a new game"),
+        }
+        assert!(matches!(
+            CLIMove::parse("newgame white"),
+            Some(CLIMove::NewGame(Cell::White, holes)) if holes.is_empty()
+        ));
+        assert!(CLIMove::parse("newgame D3").is_none());
+        assert!(CLIMove::parse("D3 F6").is_none());
+        assert!(CLIMove::parse("Z9").is_none());
+    }
+
+    #[test]
+    fn outcome_reward() {
+        let s = "BBBW
+                 BBBW
+                 BBBW
+                 BBBW";
+        let b = Board::try_from(s.to_string()).unwrap();
+        let won = Outcome::new(&b, EndState::BlackWon);
+        assert_eq!(won.disc_share, 0.5);
+        assert_eq!(won.reward(Cell::Black, Variant::Normal, 0.0), 1.0);
+        assert_eq!(won.reward(Cell::Black, Variant::Normal, 0.5), 0.875);
+        assert_eq!(won.reward(Cell::White, Variant::Normal, 0.5), 0.125);
+        // Fewer discs is the better margin in anti reversi
+        assert_eq!(won.reward(Cell::Black, Variant::Anti, 1.0), 0.25);
+        let tie = Outcome::new(&b, EndState::Tie);
+        assert_eq!(tie.reward(Cell::White, Variant::Normal, 0.0), 0.5);
+    }
+
+    #[test]
+    fn variation_repr() {
+        let pv = Variation(vec![
+            (Point::from_xy(3, 2), 0.5),
+            (Point::from_xy(2, 4), 0.25),
+        ]);
+        assert_eq!(pv.to_string(), "D3 (0.500) C5 (0.250)");
+        assert!(pv.expected_reply() == Some(Point::from_xy(2, 4)));
+        let forced = Variation(vec![(Point::from_xy(0, 0), f64::NAN)]);
+        assert_eq!(forced.to_string(), "A1");
+        assert!(forced.expected_reply().is_none());
+    }
 }
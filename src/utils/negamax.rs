@@ -0,0 +1,137 @@
+use super::{game::Game, *};
+use std::time::Instant;
+
+/// Score of a won game, above any static evaluation
+pub const WIN_SCORE: Score = Score::MAX / 2;
+
+/// Negamax score of a finished game for `color`
+pub fn final_score(result: EndState, color: Cell) -> Score {
+    match result {
+        EndState::Tie => 0,
+        r if r.won(color) => WIN_SCORE,
+        _ => -WIN_SCORE,
+    }
+}
+
+/// Positions searched between two looks at the clock
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// Alpha-beta negamax over any game; `evaluate` scores the positions
+/// at the depth limit for the side to move
+pub struct Negamax<F> {
+    pub evaluate: F,
+    /// Positions visited so far
+    pub nodes: u64,
+    /// Time at which the search gives up
+    pub deadline: Option<Instant>,
+    /// Positions after which the search gives up
+    pub max_nodes: Option<u64>,
+    /// Whether the search gave up, its results mean nothing then
+    pub stopped: bool,
+}
+
+impl<F> Negamax<F> {
+    pub fn new(evaluate: F) -> Self {
+        Self {
+            evaluate,
+            nodes: 0,
+            deadline: None,
+            max_nodes: None,
+            stopped: false,
+        }
+    }
+
+    fn is_out_of_limits(&self) -> bool {
+        self.max_nodes.is_some_and(|max| self.nodes > max)
+            || self.nodes.is_multiple_of(CLOCK_INTERVAL)
+                && self.deadline.is_some_and(|time| Instant::now() >= time)
+    }
+
+    /// Score of `game` for the side to move with the line leading to it,
+    /// the moves searched are taken back before it returns. Past the
+    /// limits it gives up and sets `stopped`
+    pub fn search<G: Game>(
+        &mut self,
+        game: &mut G,
+        depth: usize,
+        mut alpha: Score,
+        beta: Score,
+    ) -> (Score, Vec<G::Move>)
+    where
+        F: FnMut(&G) -> Score,
+    {
+        self.nodes += 1;
+        if self.stopped || self.is_out_of_limits() {
+            self.stopped = true;
+            return (0, Vec::new());
+        }
+        let moves = game.legal_moves();
+        if moves.is_empty() {
+            return (final_score(game.result(), game.to_move()), Vec::new());
+        }
+        if depth == 0 {
+            return ((self.evaluate)(game), Vec::new());
+        }
+
+        let mut best = -Score::MAX;
+        let mut pv = Vec::new();
+        for player_move in moves {
+            let undo = game.play(&player_move);
+            let (score, line) = self.search(game, depth - 1, -beta, -alpha);
+            game.undo(undo);
+            if self.stopped {
+                return (0, Vec::new());
+            }
+            let score = -score;
+            if score > best || pv.is_empty() {
+                best = score;
+                pv = std::iter::once(player_move).chain(line).collect();
+            }
+            alpha = alpha.max(best);
+            if alpha >= beta {
+                break;
+            }
+        }
+        (best, pv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::game::tests::TicTacToe;
+
+    fn solve(s: &str) -> (Score, Vec<usize>) {
+        let mut negamax = Negamax::new(|_: &TicTacToe| 0);
+        let mut game = TicTacToe::parse(s);
+        let found = negamax.search(&mut game, 9, -Score::MAX, Score::MAX);
+        assert_eq!(game.cells, TicTacToe::parse(s).cells);
+        found
+    }
+
+    #[test]
+    fn tic_tac_toe() {
+        // Perfect play draws
+        assert_eq!(solve("_________").0, 0);
+        // X wins at once
+        let (score, pv) = solve("XX_ OO_ ___");
+        assert_eq!(score, WIN_SCORE);
+        assert_eq!(pv[0], 2);
+        // O has to take an edge, a corner loses to a fork
+        let (score, pv) = solve("X__ _O_ __X");
+        assert_eq!(score, 0);
+        assert!(pv[0] % 2 == 1, "O must take an edge, took {}", pv[0]);
+        assert_eq!(solve("XO_ _X_ ___").0, -WIN_SCORE);
+    }
+
+    #[test]
+    fn stops_at_node_limit() {
+        let mut negamax = Negamax::new(|_: &TicTacToe| 0);
+        negamax.max_nodes = Some(100);
+        let mut game = TicTacToe::parse("_________");
+        negamax.search(&mut game, 9, -Score::MAX, Score::MAX);
+        assert!(negamax.stopped);
+        assert_eq!(negamax.nodes, 101);
+        assert_eq!(game.cells, TicTacToe::parse("_________").cells);
+    }
+}
@@ -1,51 +1,55 @@
 use crate::utils::*;
 use std::{char, fmt};
 
+/// Side of the largest supported board
+pub const MAX_SIZE: TileIdx = 10;
+pub const MAX_CELLS: usize = (MAX_SIZE * MAX_SIZE) as usize;
+
+/// A tile index; rows are always `MAX_SIZE` wide, so a point means the same
+/// tile on every board size and boards smaller than `MAX_SIZE` leave the
+/// rest of the row unused
 #[derive(Copy, Clone, PartialEq)]
 pub struct Point(TileIdx);
 
 impl Point {
+    /// Parses coordinates like `A1` or `J10`; whether the point fits
+    /// the board is up to the board
     #[inline]
     pub fn from_ab(ab: &str) -> Option<Self> {
         let mut chars = ab.chars();
-        let x = chars.next().and_then(|c| c.to_uppercase().next()).unwrap()
-            as TileIdx;
-        let y = chars.next().and_then(|c| c.to_digit(10)).unwrap() as TileIdx;
-        if (1..=8).contains(&y) && (65..=72).contains(&x) {
-            Some(Self::from_xy(x - 65, y - 1))
+        let x = chars.next().and_then(|c| c.to_uppercase().next())? as i32;
+        let y = chars.as_str().parse::<i32>().ok()?;
+        let size = MAX_SIZE as i32;
+        if (1..=size).contains(&y) && (65..65 + size).contains(&x) {
+            Some(Self::from_xy((x - 65) as TileIdx, (y - 1) as TileIdx))
         } else {
             None
         }
     }
 
     #[inline]
-    pub fn to_ab(&self) -> String {
+    pub fn to_ab(self) -> String {
         let (x, y) = self.to_xy();
-        format!(
-            "{}{}",
-            char::from_u32(x as u32 + 65).unwrap(),
-            (y + 1).to_string()
-        )
+        format!("{}{}", char::from_u32(x as u32 + 65).unwrap(), (y + 1))
     }
 
-    #[inline]
     pub fn from_idx(idx: TileIdx) -> Self {
         Self(idx)
     }
 
     #[allow(dead_code)]
-    pub fn to_idx(&self) -> TileIdx {
+    pub fn to_idx(self) -> TileIdx {
         self.0
     }
 
     #[inline]
     pub fn from_xy(x: TileIdx, y: TileIdx) -> Self {
-        Self(y * 8 + x)
+        Self(y * MAX_SIZE + x)
     }
 
     #[inline]
-    pub fn to_xy(&self) -> (TileIdx, TileIdx) {
-        (self.0 % 8, self.0 / 8)
+    pub fn to_xy(self) -> (TileIdx, TileIdx) {
+        (self.0 % MAX_SIZE, self.0 / MAX_SIZE)
     }
 
     #[inline]
@@ -54,28 +58,32 @@ impl Point {
     }
 
     #[allow(dead_code)]
-    pub fn mirror(&self) -> [Self; 4] {
+    pub fn mirror(&self, size: TileIdx) -> [Self; 4] {
         let (x, y) = self.to_xy();
+        let last = size - 1;
         [
             Self::from_xy(x, y),
-            Self::from_xy(7 - x, y),
-            Self::from_xy(x, 7 - y),
-            Self::from_xy(7 - x, 7 - y),
+            Self::from_xy(last - x, y),
+            Self::from_xy(x, last - y),
+            Self::from_xy(last - x, last - y),
         ]
     }
 
+    /// Reflects the point into the top left quadrant of a `size` board
     #[inline]
-    pub fn unmirror4(&self) -> Self {
+    pub fn unmirror4(&self, size: TileIdx) -> Self {
         let (x, y) = self.to_xy();
+        let (half, last) = (size / 2, size - 1);
         Self::from_xy(
-            if x < 4 { x } else { 7 - x },
-            if y < 4 { y } else { 7 - y },
+            if x < half { x } else { last - x },
+            if y < half { y } else { last - y },
         )
     }
 
+    /// Same as `unmirror4`, also reflecting over the main diagonal
     #[inline]
-    pub fn unmirror8(&self) -> Self {
-        let (x, y) = self.unmirror4().to_xy();
+    pub fn unmirror8(&self, size: TileIdx) -> Self {
+        let (x, y) = self.unmirror4(size).to_xy();
         if x > y {
             Self::from_xy(x, y)
         } else {
@@ -94,6 +102,23 @@ impl fmt::Debug for Point {
 #[test]
 fn mirror1() {
     let p = Point::from_xy(6, 5);
-    assert_eq!(p.unmirror4(), Point::from_xy(1, 2));
-    assert_eq!(p.unmirror8(), Point::from_xy(2, 1));
+    assert_eq!(p.unmirror4(8), Point::from_xy(1, 2));
+    assert_eq!(p.unmirror8(8), Point::from_xy(2, 1));
+}
+
+#[test]
+fn mirror_sizes() {
+    let p = Point::from_xy(5, 4);
+    assert_eq!(p.unmirror4(6), Point::from_xy(0, 1));
+    assert_eq!(p.unmirror4(10), Point::from_xy(4, 4));
+}
+
+#[test]
+fn ab_coords() {
+    assert_eq!(Point::from_ab("a1"), Some(Point::from_xy(0, 0)));
+    assert_eq!(Point::from_ab("J10"), Some(Point::from_xy(9, 9)));
+    assert_eq!(Point::from_xy(9, 9).to_ab(), "J10");
+    assert_eq!(Point::from_ab("K1"), None);
+    assert_eq!(Point::from_ab("A11"), None);
+    assert_eq!(Point::from_ab("pass"), None);
 }
@@ -0,0 +1,313 @@
+use super::{board::Board, point::Point, zobrist, *};
+use rand::Rng;
+use std::{convert::TryFrom, fs};
+
+/// A board together with the side to move
+#[derive(Clone, Copy)]
+pub struct Position {
+    pub board: Board,
+    pub to_move: Cell,
+}
+
+impl Position {
+    pub fn initial(
+        size: TileIdx,
+        black_holes: &[Point],
+    ) -> Result<Self, String> {
+        Ok(Self {
+            board: Board::new(size, black_holes)?,
+            to_move: Cell::Black,
+        })
+    }
+
+    /// Builds the starting position from `--position`, `--moves` and
+    /// `--random-opening`, applied in that order on top of each other
+    pub fn from_args(
+        matches: &ArgMatches,
+        black_holes: &[Point],
+    ) -> Result<Self, String> {
+        let size = board_size(matches);
+        let mut position = match matches.value_of("position") {
+            Some(path) => {
+                let contents = fs::read_to_string(path)
+                    .map_err(|e| format!("Can't read {}: {}", path, e))?;
+                let mut position = Self::parse(&contents)?;
+                if position.board.size != size {
+                    return Err(format!(
+                        "Position is {0}x{0}, expected {1}x{1}",
+                        position.board.size, size
+                    ));
+                }
+                position.add_black_holes(black_holes)?;
+                position
+            }
+            None => Self::initial(size, black_holes)?,
+        };
+        if let Some(moves) = matches.value_of("moves") {
+            position.play_sequence(moves)?;
+        }
+        if let Some(nmoves) = matches.value_of("random_opening") {
+            let nmoves = nmoves
+                .parse::<usize>()
+                .map_err(|_| format!("Bad number of moves: {}", nmoves))?;
+            position.play_random(nmoves, &mut rng::thread_rng());
+        }
+        position.validate()?;
+        Ok(position)
+    }
+
+    /// Parses a board in the `Board::try_from` format, optionally followed
+    /// by a `black` or `white` line for the side to move (black by default)
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut to_move = Cell::Black;
+        let mut board_str = String::new();
+        for line in s.lines().map(str::trim) {
+            match line {
+                "black" => to_move = Cell::Black,
+                "white" => to_move = Cell::White,
+                _ => {
+                    board_str.push_str(line);
+                    board_str.push('\n');
+                }
+            }
+        }
+        let board = Board::try_from(board_str)?;
+        Ok(Self { board, to_move })
+    }
+
+    pub fn add_black_holes(
+        &mut self,
+        black_holes: &[Point],
+    ) -> Result<(), String> {
+        for &bh in black_holes {
+            self.board.add_black_hole(bh)?;
+        }
+        Ok(())
+    }
+
+    pub fn allowed_moves(&self) -> AllowedMoves {
+        self.board.allowed_moves(self.to_move)
+    }
+
+    /// Zobrist hash of the board and the side to move
+    pub fn hash(&self) -> u64 {
+        self.board.hash ^ zobrist::side_key(self.to_move)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.allowed_moves().is_empty()
+            && self.board.allowed_moves(!self.to_move).is_empty()
+    }
+
+    /// Plays a move (`None` for a pass) if it's legal
+    pub fn play(&mut self, point: Option<Point>) -> Result<(), String> {
+        let allowed = self.allowed_moves();
+        match point {
+            Some(p) if !self.board.contains(p) => {
+                return Err(format!(
+                    "{} is off the {1}x{1} board",
+                    p.to_ab(),
+                    self.board.size
+                ));
+            }
+            Some(p) => {
+                let pl_move = allowed
+                    .iter()
+                    .find(|(ti, _)| *ti == p)
+                    .ok_or(format!("Illegal move: {}", p.to_ab()))?;
+                self.board.apply_move(pl_move, self.to_move);
+            }
+            None if !allowed.is_empty() => {
+                return Err("Pass while having moves".to_string());
+            }
+            None => {}
+        }
+        self.to_move = !self.to_move;
+        Ok(())
+    }
+
+    /// Plays whitespace separated moves like `D3 C5 pass F6`,
+    /// passes may also be omitted
+    pub fn play_sequence(&mut self, moves: &str) -> Result<(), String> {
+        for token in moves.split_whitespace() {
+            if token == "pass" {
+                self.play(None)?;
+                continue;
+            }
+            let point = Point::from_ab(token)
+                .ok_or(format!("Bad coordinate: {}", token))?;
+            if self.allowed_moves().is_empty() && !self.is_over() {
+                self.play(None)?;
+            }
+            self.play(Some(point))?;
+        }
+        Ok(())
+    }
+
+    /// Plays `nmoves` uniformly random moves, stopping early if the game
+    /// ends; passes don't count as moves
+    pub fn play_random(&mut self, nmoves: usize, rng: &mut impl Rng) {
+        let mut played = 0;
+        while played < nmoves && !self.is_over() {
+            let allowed = self.allowed_moves();
+            if allowed.is_empty() {
+                self.play(None).unwrap();
+                continue;
+            }
+            let pl_move = &allowed[rng.gen_range(0, allowed.len())];
+            self.play(Some(pl_move.0)).unwrap();
+            played += 1;
+        }
+    }
+
+    /// Checks that the position could occur in a game: the four center
+    /// tiles are discs, all discs are connected to them and there is
+    /// still something to play. A side to move that has to pass is
+    /// switched to the opponent.
+    pub fn validate(&mut self) -> Result<(), String> {
+        let board = &self.board;
+        let (lo, hi) = (board.size / 2 - 1, board.size / 2);
+        let center = [(lo, lo), (lo, hi), (hi, lo), (hi, hi)];
+        if center
+            .iter()
+            .any(|&(x, y)| !board.at(Point::from_xy(x, y)).is_disc())
+        {
+            return Err("Center tiles must be occupied by discs".to_string());
+        }
+
+        // Every disc was placed next to an existing one
+        let mut reached = vec![Point::from_xy(lo, lo)];
+        let mut queue = reached.clone();
+        while let Some(p) = queue.pop() {
+            let (x, y) = p.to_xy();
+            for (dx, dy) in TRAVERSE_DIRECTIONS.iter() {
+                let (nx, ny) = (x + dx, y + dy);
+                let range = 0..board.size;
+                if !range.contains(&nx) || !range.contains(&ny) {
+                    continue;
+                }
+                let next = Point::from_xy(nx, ny);
+                if board.at(next).is_disc() && !reached.contains(&next) {
+                    reached.push(next);
+                    queue.push(next);
+                }
+            }
+        }
+        let ndiscs = board.count(Cell::Black) + board.count(Cell::White);
+        if reached.len() != ndiscs {
+            return Err("Discs must be connected to the center".to_string());
+        }
+
+        if self.is_over() {
+            return Err("Game is already over".to_string());
+        }
+        if self.allowed_moves().is_empty() {
+            self.to_move = !self.to_move;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_play_sequence() {
+        let mut pos = Position::initial(8, &[]).unwrap();
+        pos.play_sequence("D3 C5 F6").unwrap();
+        assert!(pos.to_move == Cell::White);
+        assert_eq!(pos.board.count(Cell::Black), 5);
+        assert_eq!(pos.board.count(Cell::White), 2);
+        assert!(pos.play_sequence("A1").is_err());
+
+        let mut small = Position::initial(6, &[]).unwrap();
+        let err = small.play_sequence("G4").unwrap_err();
+        assert_eq!(err, "G4 is off the 6x6 board");
+    }
+
+    #[test]
+    fn test_parse_and_validate() {
+        let s = "________
+                 ________
+                 ________
+                 ___WB___
+                 ___BW___
+                 ____B___
+                 ________
+                 ________
+                 white";
+        let mut pos = Position::parse(s).unwrap();
+        assert!(pos.to_move == Cell::White);
+        assert!(pos.validate().is_ok());
+
+        let s = "B_______
+                 ________
+                 ________
+                 ___WB___
+                 ___BW___
+                 ________
+                 ________
+                 ________";
+        let mut detached = Position::parse(s).unwrap();
+        assert!(detached.validate().is_err());
+
+        let s = "________
+                 ________
+                 ________
+                 ___W____
+                 ___BW___
+                 ________
+                 ________
+                 ________";
+        let mut no_center = Position::parse(s).unwrap();
+        assert!(no_center.validate().is_err());
+    }
+
+    #[test]
+    fn test_hash() {
+        let mut a = Position::initial(8, &[]).unwrap();
+        let mut b = a;
+        // Two move orders reaching the same position
+        a.play_sequence("C4 C3 D3 E3").unwrap();
+        b.play_sequence("D3 C3 C4 E3").unwrap();
+        assert!(a.board == b.board);
+        assert_eq!(zobrist::hash(&a), zobrist::hash(&b));
+        b.to_move = !b.to_move;
+        assert_ne!(zobrist::hash(&a), zobrist::hash(&b));
+    }
+
+    #[test]
+    fn test_black_holes_on_discs() {
+        let mut pos = Position::parse("_WB_\nBWBW\nWBWB\n_BW_").unwrap();
+        assert!(pos.add_black_holes(&[Point::from_xy(0, 0)]).is_ok());
+        assert_eq!(pos.board.count(Cell::BlackHole), 1);
+        assert!(pos.add_black_holes(&[Point::from_xy(1, 0)]).is_err());
+        assert!(pos.add_black_holes(&[Point::from_xy(5, 5)]).is_err());
+    }
+
+    #[test]
+    fn test_validate_passes() {
+        // White has no moves, so black must play
+        let s = "_WBB
+                 BBBB
+                 BBBB
+                 BBBB
+                 white";
+        let mut pos = Position::parse(s).unwrap();
+        assert!(pos.validate().is_ok());
+        assert!(pos.to_move == Cell::Black);
+    }
+
+    #[test]
+    fn test_random_opening() {
+        let mut pos = Position::initial(8, &[Point::from_xy(0, 0)]).unwrap();
+        pos.play_random(6, &mut StdRng::seed_from_u64(1));
+        assert_eq!(
+            pos.board.count(Cell::Black) + pos.board.count(Cell::White),
+            10
+        );
+        assert!(pos.validate().is_ok());
+    }
+}
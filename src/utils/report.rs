@@ -0,0 +1,190 @@
+use super::*;
+use std::time::Duration;
+
+/// What the search found out about a single root move
+#[derive(Clone)]
+pub struct RootMoveStats {
+    pub point: Point,
+    /// Playouts (MCTS) or searched nodes (minimax) spent on the move
+    pub visits: u64,
+    /// Won playouts, always 0 for minimax
+    pub wins: u64,
+    pub score: f64,
+}
+
+/// Statistics of a single search, returned by every bot with its move
+#[derive(Clone)]
+pub struct SearchReport {
+    pub color: Cell,
+    /// Nodes for minimax, playouts for MCTS
+    pub nodes: u64,
+    /// Deepest ply the search reached
+    pub depth: usize,
+    pub elapsed: Duration,
+    pub root_moves: Vec<RootMoveStats>,
+    pub best_score: f64,
+    pub pv: Variation,
+    /// None while the search has no transposition table
+    pub tt_hit_rate: Option<f64>,
+}
+
+impl SearchReport {
+    pub fn new(color: Cell) -> Self {
+        Self {
+            color,
+            nodes: 0,
+            depth: 0,
+            elapsed: Duration::default(),
+            root_moves: Vec::new(),
+            best_score: f64::NAN,
+            pv: Variation::default(),
+            tt_hit_rate: None,
+        }
+    }
+
+    /// Report for a move that didn't require any search
+    pub fn forced(color: Cell, point: Point) -> Self {
+        let mut report = Self::new(color);
+        report.root_moves.push(RootMoveStats {
+            point,
+            visits: 0,
+            wins: 0,
+            score: f64::NAN,
+        });
+        report.pv = Variation(vec![(point, f64::NAN)]);
+        report
+    }
+
+    /// The move the search chose, it leads the principal variation
+    pub fn best_move(&self) -> Option<Point> {
+        self.pv.0.first().map(|(p, _)| *p)
+    }
+
+    pub fn to_json(&self) -> String {
+        let root_moves = self
+            .root_moves
+            .iter()
+            .map(|m| {
+                format!(
+                    "{{\"move\":\"{}\",\"visits\":{},\"wins\":{},\"score\":{}}}",
+                    m.point.to_ab(),
+                    m.visits,
+                    m.wins,
+                    json_f64(m.score)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let pv = self
+            .pv
+            .0
+            .iter()
+            .map(|(p, score)| {
+                format!(
+                    "{{\"move\":\"{}\",\"score\":{}}}",
+                    p.to_ab(),
+                    json_f64(*score)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let best_move = match self.best_move() {
+            Some(p) => format!("\"{}\"", p.to_ab()),
+            None => "null".to_string(),
+        };
+        format!(
+            concat!(
+                "{{\"color\":\"{}\",\"move\":{},\"nodes\":{},\"depth\":{},",
+                "\"elapsed_ms\":{},\"best_score\":{},\"tt_hit_rate\":{},",
+                "\"pv\":[{}],\"root_moves\":[{}]}}"
+            ),
+            color_name(self.color),
+            best_move,
+            self.nodes,
+            self.depth,
+            self.elapsed.as_millis(),
+            json_f64(self.best_score),
+            self.tt_hit_rate.map_or("null".to_string(), json_f64),
+            pv,
+            root_moves,
+        )
+    }
+}
+
+impl fmt::Display for SearchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "nodes: {}; depth: {}; time: {}ms; best score: {:.3}",
+            self.nodes,
+            self.depth,
+            self.elapsed.as_millis(),
+            self.best_score
+        )?;
+        let root_moves = self
+            .root_moves
+            .iter()
+            .map(|m| format!("{} {}/{}", m.point.to_ab(), m.wins, m.visits))
+            .collect::<Vec<_>>();
+        writeln!(f, "root moves: [{}]", root_moves.join(", "))?;
+        if let Some(rate) = self.tt_hit_rate {
+            writeln!(f, "tt hit rate: {:.3}", rate)?;
+        }
+        write!(f, "pv: {}", self.pv)
+    }
+}
+
+fn color_name(color: Cell) -> &'static str {
+    match color {
+        Cell::Black => "black",
+        Cell::White => "white",
+        _ => "none",
+    }
+}
+
+// JSON has no representation for NaN and infinities
+fn json_f64(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_json() {
+        let point = Point::from_xy(3, 2);
+        let mut report = SearchReport::new(Cell::Black);
+        report.nodes = 10;
+        report.depth = 2;
+        report.best_score = 0.5;
+        report.root_moves.push(RootMoveStats {
+            point,
+            visits: 10,
+            wins: 5,
+            score: 0.5,
+        });
+        report.pv = Variation(vec![(point, 0.5)]);
+        assert_eq!(
+            report.to_json(),
+            concat!(
+                "{\"color\":\"black\",\"move\":\"D3\",\"nodes\":10,",
+                "\"depth\":2,\"elapsed_ms\":0,\"best_score\":0.5,",
+                "\"tt_hit_rate\":null,\"pv\":[{\"move\":\"D3\",\"score\":0.5}],",
+                "\"root_moves\":[{\"move\":\"D3\",\"visits\":10,\"wins\":5,",
+                "\"score\":0.5}]}"
+            )
+        );
+    }
+
+    #[test]
+    fn forced_report_json() {
+        let report = SearchReport::forced(Cell::White, Point::from_xy(0, 0));
+        assert!(report.to_json().contains("\"best_score\":null"));
+        assert!(report.to_json().contains("\"move\":\"A1\""));
+    }
+}
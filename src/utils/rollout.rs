@@ -0,0 +1,292 @@
+use super::*;
+use rand::Rng;
+
+/// How playouts pick their moves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Policy {
+    /// Uniformly random moves
+    Random,
+    /// The best move by the static evaluation, a random one with the
+    /// given probability
+    EpsilonGreedy(f64),
+    /// Softmax with the given temperature over corner, X-square and
+    /// mobility features of the moves. Mobility generates the replies
+    /// to every candidate, which makes playouts about 5 times slower
+    /// than random ones
+    Softmax(f64),
+}
+
+/// Feature weights of the softmax policy, in regular reversi
+const CORNER_WEIGHT: f64 = 3.0;
+const X_SQUARE_WEIGHT: f64 = -2.0;
+const MOBILITY_WEIGHT: f64 = -0.2;
+
+/// Playout policy with an optional cutoff
+#[derive(Clone, Copy, Debug)]
+pub struct Rollout {
+    pub policy: Policy,
+    /// Moves after which the playout stops and the static evaluation
+    /// decides the result
+    pub cutoff: Option<usize>,
+}
+
+impl Policy {
+    /// `--rollout` overrides the policy the bot uses by default
+    pub fn from_args(matches: &ArgMatches) -> Option<Self> {
+        let parse = |name| {
+            matches
+                .value_of(name)
+                .map(|it| it.parse::<f64>().unwrap())
+                .unwrap()
+        };
+        match matches.value_of("rollout") {
+            Some("random") => Some(Policy::Random),
+            Some("greedy") => Some(Policy::EpsilonGreedy(0.0)),
+            Some("epsilon-greedy") => {
+                Some(Policy::EpsilonGreedy(parse("rollout_epsilon")))
+            }
+            Some("softmax") => {
+                Some(Policy::Softmax(parse("rollout_temperature")))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Rollout {
+    /// Plays out from `board` with `color` to move, recording every move
+    /// with its color in `played`
+    pub fn play(
+        &self,
+        mut board: Board,
+        mut color: Cell,
+        variant: Variant,
+        rng: &mut impl Rng,
+        played: &mut Vec<(Point, Cell)>,
+    ) -> Outcome {
+        let mut nmoves = 0;
+        loop {
+            if self.cutoff.is_some_and(|cutoff| nmoves >= cutoff) {
+                return evaluate(&board, variant);
+            }
+            let mut allowed = board.allowed_moves(color);
+            let win = wincheck(&board, &allowed, variant, color);
+            if win.is_over() {
+                return Outcome::new(&board, win);
+            }
+            if allowed.is_empty() {
+                color = !color;
+                allowed = board.allowed_moves(color);
+            }
+            let index = if allowed.len() == 1 {
+                0
+            } else {
+                self.pick(&mut board, &allowed, color, variant, rng)
+            };
+            let player_move = &allowed[index];
+            board.apply_move(player_move, color);
+            played.push((player_move.0, color));
+            color = !color;
+            nmoves += 1;
+        }
+    }
+
+    fn pick(
+        &self,
+        board: &mut Board,
+        allowed: &AllowedMoves,
+        color: Cell,
+        variant: Variant,
+        rng: &mut impl Rng,
+    ) -> usize {
+        match self.policy {
+            Policy::EpsilonGreedy(epsilon) if rng.gen::<f64>() >= epsilon => {
+                let scores = allowed.iter().map(|player_move| {
+                    let undo = board.make_move(player_move, color);
+                    let score = variant.evaluate(board, color);
+                    board.unmake_move(undo);
+                    score
+                });
+                let mut best = (0, Score::MIN);
+                for (index, score) in scores.enumerate() {
+                    if score > best.1 {
+                        best = (index, score);
+                    }
+                }
+                best.0
+            }
+            Policy::Softmax(temperature) => {
+                let weights = allowed
+                    .iter()
+                    .map(|m| features(board, m, color, variant) / temperature)
+                    .collect::<Vec<_>>();
+                let max = weights.iter().cloned().fold(f64::MIN, f64::max);
+                let exps =
+                    weights.iter().map(|w| (w - max).exp()).collect::<Vec<_>>();
+                let mut pick = rng.gen_range(0.0, exps.iter().sum::<f64>());
+                for (index, e) in exps.iter().enumerate() {
+                    if pick < *e {
+                        return index;
+                    }
+                    pick -= e;
+                }
+                exps.len() - 1
+            }
+            _ => rng.gen_range(0, allowed.len()),
+        }
+    }
+}
+
+/// Weighted corner, X-square and opponent mobility features of a move;
+/// corners and X-squares count the other way round in anti reversi
+fn features(
+    board: &mut Board,
+    player_move: &PlayerMove,
+    color: Cell,
+    variant: Variant,
+) -> f64 {
+    let (x, y) = player_move.0.to_xy();
+    let last = board.size - 1;
+    let edge = |i: TileIdx| i == 0 || i == last;
+    let next_to_edge = |i: TileIdx| i == 1 || i == last - 1;
+    let mut tiles = 0.0;
+    if edge(x) && edge(y) {
+        tiles += CORNER_WEIGHT;
+    }
+    if next_to_edge(x) && next_to_edge(y) {
+        let corner = Point::from_xy(
+            if x == 1 { 0 } else { last },
+            if y == 1 { 0 } else { last },
+        );
+        if board.at(corner).is_empty() {
+            tiles += X_SQUARE_WEIGHT;
+        }
+    }
+    tiles *= variant.sign() as f64;
+    let undo = board.make_move(player_move, color);
+    let mobility = board.allowed_moves(!color).len() as f64;
+    board.unmake_move(undo);
+    tiles + MOBILITY_WEIGHT * mobility
+}
+
+/// Result of a cut off playout by the sign of the static evaluation
+fn evaluate(board: &Board, variant: Variant) -> Outcome {
+    let score = variant.evaluate(board, Cell::Black);
+    let result = match score {
+        s if s > 0 => EndState::BlackWon,
+        s if s < 0 => EndState::WhiteWon,
+        _ => EndState::Tie,
+    };
+    Outcome::new(board, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::convert::TryFrom;
+
+    const POLICIES: [Policy; 3] = [
+        Policy::Random,
+        Policy::EpsilonGreedy(0.1),
+        Policy::Softmax(1.0),
+    ];
+
+    #[test]
+    fn seeded_playouts_repeat() {
+        let board = Board::new(6, &[Point::from_xy(0, 0)]).unwrap();
+        for &policy in POLICIES.iter() {
+            let rollout = Rollout {
+                policy,
+                cutoff: None,
+            };
+            let playouts = |seed| {
+                let mut rng = StdRng::seed_from_u64(seed);
+                (0..20)
+                    .map(|_| {
+                        let mut played = Vec::new();
+                        let outcome = rollout.play(
+                            board,
+                            Cell::Black,
+                            Variant::Anti,
+                            &mut rng,
+                            &mut played,
+                        );
+                        (outcome.result, played)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            assert!(playouts(1) == playouts(1));
+            // Without a cutoff every playout ends the game
+            for (_, played) in playouts(2) {
+                let mut board = board;
+                for (p, color) in played {
+                    let player_move = board
+                        .allowed_moves(color)
+                        .into_iter()
+                        .find(|m| m.0 == p)
+                        .unwrap();
+                    board.apply_move(&player_move, color);
+                }
+                assert!(board.allowed_moves(Cell::Black).is_empty());
+                assert!(board.allowed_moves(Cell::White).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn cutoff_stops_early() {
+        let board = Board::new(8, &[]).unwrap();
+        let rollout = Rollout {
+            policy: Policy::Random,
+            cutoff: Some(10),
+        };
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut played = Vec::new();
+        let outcome = rollout.play(
+            board,
+            Cell::Black,
+            Variant::Normal,
+            &mut rng,
+            &mut played,
+        );
+        assert_eq!(played.len(), 10);
+        assert!(outcome.result.is_over());
+    }
+
+    #[test]
+    fn softmax_prefers_corners() {
+        let s = "_WB_____
+                 ________
+                 ________
+                 ___WB___
+                 ___BW___
+                 ________
+                 ________
+                 ________";
+        let mut board = Board::try_from(s.to_string()).unwrap();
+        let allowed = board.allowed_moves(Cell::Black);
+        let corner = allowed.iter().position(|m| m.0 == Point::from_xy(0, 0));
+        let rollout = Rollout {
+            policy: Policy::Softmax(0.5),
+            cutoff: None,
+        };
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut picks = [0, 0];
+        for &variant in [Variant::Normal, Variant::Anti].iter() {
+            for _ in 0..200 {
+                let index = rollout.pick(
+                    &mut board,
+                    &allowed,
+                    Cell::Black,
+                    variant,
+                    &mut rng,
+                );
+                if Some(index) == corner {
+                    picks[(variant == Variant::Anti) as usize] += 1;
+                }
+            }
+        }
+        assert!(picks[0] > 150 && picks[1] < 20);
+    }
+}
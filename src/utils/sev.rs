@@ -29,9 +29,23 @@ const TILE_HEURISTICS: [[i32; 8]; 8] = [
     [ 410,  23,  13,   8,   8,  13,  23, 410 ],
 ];
 
+/// Maps a tile of a `size` board onto the matching tile of the top left
+/// 8x8 quadrant: corners, edges and the center keep their roles, extra
+/// middle rows and columns of bigger boards fold into the third one
 #[inline]
-fn tile_cost_1(pos: Point) -> Score {
-    let (x, y) = pos.unmirror8().to_xy();
+fn quadrant_xy(pos: Point, size: TileIdx) -> (TileIdx, TileIdx) {
+    let (x, y) = pos.unmirror8(size).to_xy();
+    if size == 8 {
+        return (x, y);
+    }
+    let half = size / 2;
+    let scale = |i: TileIdx| if i == half - 1 { 3 } else { i.min(2) };
+    (scale(x), scale(y))
+}
+
+#[inline]
+fn tile_cost_1(pos: Point, size: TileIdx) -> Score {
+    let (x, y) = quadrant_xy(pos, size);
     if (x, y) == (0, 0) {
         GOOD_CORNER
     } else if x == 1 && (y == 0 || y == 1) {
@@ -51,8 +65,9 @@ fn tile_cost_1(pos: Point) -> Score {
 //                -87
 
 #[inline]
-pub fn tile_cost_2(pos: Point) -> Score {
-    let i = pos.unmirror8().to_idx();
+pub fn tile_cost_2(pos: Point, size: TileIdx) -> Score {
+    let (x, y) = quadrant_xy(pos, size);
+    let i = y * 8 + x;
 
     match i {
         0 => 410,
@@ -94,11 +109,10 @@ pub fn tile_cost_2(pos: Point) -> Score {
 pub fn static_eval_with_weights_1(board: &Board, player_color: Cell) -> Score {
     let opposite = player_color.opposite();
     board
-        .0
-        .iter()
-        .enumerate()
-        .map(|(index, &t)| {
-            let cost = tile_cost_1(Point::from_idx(index as i8));
+        .points()
+        .map(|p| {
+            let t = board.at(p);
+            let cost = tile_cost_1(p, board.size);
             if t == player_color {
                 cost
             } else if t == opposite {
@@ -114,11 +128,10 @@ pub fn static_eval_with_weights_1(board: &Board, player_color: Cell) -> Score {
 pub fn static_eval_with_weights_2(board: &Board, player_color: Cell) -> Score {
     let opposite = player_color.opposite();
     board
-        .0
-        .iter()
-        .enumerate()
-        .map(|(index, &t)| {
-            let cost = tile_cost_2(Point::from_idx(index as i8));
+        .points()
+        .map(|p| {
+            let t = board.at(p);
+            let cost = tile_cost_2(p, board.size);
             if t == player_color {
                 cost
             } else if t == opposite {
@@ -134,16 +147,18 @@ pub fn static_eval_with_weights_2(board: &Board, player_color: Cell) -> Score {
 pub fn sev3(board: &Board, color: Cell, is_depth_even: bool) -> Score {
     let mut count = 0;
 
-    for i in 0..64 {
-        let p = Point::from_idx(i);
-        let (x, y) = p.to_xy();
+    for p in board.points() {
+        let tile = board.at(p);
+        if tile == Cell::BlackHole {
+            continue;
+        }
+
         let mut a = board.nempty_neighbours(p);
         if a == 0 {
             a = 6;
         }
 
-        let heu = TILE_HEURISTICS[y as usize][x as usize] + (8 * a);
-        let tile = board.at(p);
+        let heu = tile_weight(board, p) + (8 * a);
 
         if tile.is_empty() {
             if is_depth_even {
@@ -164,11 +179,34 @@ pub fn sev3(board: &Board, color: Cell, is_depth_even: bool) -> Score {
     count
 }
 
+/// How good owning the tile is in regular reversi
+#[inline]
+pub fn tile_weight(board: &Board, pos: Point) -> Score {
+    let (x, y) = quadrant_xy(pos, board.size);
+    if x == 1 && y <= 1 && corner_is_hole(board, pos) {
+        0
+    } else {
+        TILE_HEURISTICS[y as usize][x as usize]
+    }
+}
+
+/// C and X squares are only dangerous while their corner can be taken
+#[inline]
+fn corner_is_hole(board: &Board, pos: Point) -> bool {
+    let (x, y) = pos.to_xy();
+    let (half, last) = (board.size / 2, board.size - 1);
+    let corner = Point::from_xy(
+        if x < half { 0 } else { last },
+        if y < half { 0 } else { last },
+    );
+    board.at(corner) == Cell::BlackHole
+}
+
 #[inline]
 pub fn mainlines_penalty(board: &Board, ml: MainLine, my_color: Cell) -> i32 {
     let penalty = 86;
     let line = board.mainline(ml);
-    let size = 8;
+    let size = line.len();
     let mut count = 0;
 
     if line[1] == my_color && line[0].is_empty() {
@@ -179,7 +217,8 @@ pub fn mainlines_penalty(board: &Board, ml: MainLine, my_color: Cell) -> i32 {
                 break;
             }
         }
-        if i != size - 2 {
+        // A run ending in a black hole can't be flipped along the line
+        if i != size - 2 && line[i] != Cell::BlackHole {
             count += penalty;
             if line[i + 1] == my_color {
                 count += penalty;
@@ -210,7 +249,7 @@ pub fn mainlines_penalty(board: &Board, ml: MainLine, my_color: Cell) -> i32 {
                 break;
             }
         }
-        if i != size - 2 {
+        if i != size - 2 && line[size - 1 - i] != Cell::BlackHole {
             count += penalty;
             if line[size - 2 - i] == my_color {
                 count += penalty;
@@ -238,10 +277,69 @@ pub fn mainlines_penalty(board: &Board, ml: MainLine, my_color: Cell) -> i32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_quadrant_sizes() {
+        for &size in [6, 8, 10].iter() {
+            let last = size - 1;
+            let corner = Point::from_xy(last, 0);
+            let x_square = Point::from_xy(last - 1, last - 1);
+            assert_eq!(tile_cost_2(corner, size), 410);
+            assert_eq!(tile_cost_2(x_square, size), -75);
+            let center = Point::from_xy(size / 2, size / 2 - 1);
+            assert_eq!(tile_cost_2(center, size), -87);
+        }
+    }
+
+    #[test]
+    fn test_holes_arent_discs() {
+        let s = "HHHH
+                 HWBH
+                 HBWH
+                 HHHH";
+        let b = Board::try_from(s.to_string()).unwrap();
+        // Discs cancel out, holes used to count as opponent discs
+        assert_eq!(sev3(&b, Cell::Black, true), 0);
+        assert_eq!(sev3(&b, Cell::White, true), 0);
+    }
+
+    #[test]
+    fn test_mainline_run_ending_in_hole() {
+        let mut b = Board::initial(&[]).unwrap();
+        b.place(Point::from_xy(1, 0), Cell::Black)
+            .place(Point::from_xy(2, 0), Cell::Black);
+        assert!(mainlines_penalty(&b, MainLine::Top, Cell::Black) > 0);
+        b.place(Point::from_xy(3, 0), Cell::BlackHole);
+        assert_eq!(mainlines_penalty(&b, MainLine::Top, Cell::Black), 0);
+    }
+
+    #[test]
+    fn test_corner_hole_x_square() {
+        let x_square = Point::from_xy(1, 1);
+        let mut open = Board::initial(&[]).unwrap();
+        open.place(x_square, Cell::Black);
+        let mut closed = Board::initial(&[Point::from_xy(0, 0)]).unwrap();
+        closed.place(x_square, Cell::Black);
+        assert!(
+            sev3(&closed, Cell::Black, true) > sev3(&open, Cell::Black, true)
+        );
+    }
+
+    #[test]
+    fn test_sev_symmetric_start() {
+        for &size in [6, 8, 10].iter() {
+            let b = Board::new(size, &[]).unwrap();
+            assert_eq!(
+                sev3(&b, Cell::Black, true),
+                sev3(&b, Cell::White, true)
+            );
+        }
+    }
 
     #[test]
     fn test_score_1() {
-        let mut b = Board::initial(Some(Point::from_xy(1, 1)));
+        let mut b = Board::initial(&[Point::from_xy(1, 1)]).unwrap();
         b.place(Point::from_xy(0, 0), Cell::Black)
             .place(Point::from_xy(6, 0), Cell::Black);
         assert_eq!(
@@ -1,34 +1,100 @@
-use super::*;
-use rand::random;
+use super::{game::Game, *};
+use rand::Rng;
 use std::{
     cell::RefCell,
     rc::{Rc, Weak},
 };
 
-pub struct Node {
-    pub board: Board,
+/// Node of a plain UCT tree over any game with moves of type `M`
+pub struct Node<M> {
     pub color: Cell,
     pub nwins: u64,
     pub nvisits: u64,
-    pub children: Vec<NodeRef>,
-    pub parent: Option<Weak<RefCell<Node>>>,
-    pub player_move: Option<PlayerMove>,
+    /// Sum of the playout rewards, a won playout is worth 1
+    pub reward: f64,
+    pub children: Vec<NodeRef<M>>,
+    pub parent: Option<Weak<RefCell<Node<M>>>>,
+    pub player_move: Option<M>,
     pub leaf: bool,
 }
 
-pub type NodeRef = Rc<RefCell<Node>>;
+pub type NodeRef<M> = Rc<RefCell<Node<M>>>;
 
-impl Node {
-    pub fn new(
-        board: Board,
-        color: Cell,
-        player_move: Option<PlayerMove>,
-    ) -> NodeRef {
+/// What the walks shared by the MCTS trees need to know about a node
+pub trait TreeNode: Sized {
+    type Move: Clone;
+
+    /// Move leading into the node, none at the root
+    fn player_move(&self) -> Option<&Self::Move>;
+
+    fn nvisits(&self) -> u64;
+
+    /// Value of the node's move for the side that played it
+    fn score(&self) -> f64;
+
+    fn children(&self) -> &[Rc<RefCell<Self>>];
+
+    /// Follows the most visited children starting from `noderef`,
+    /// pairing each move with its score
+    fn principal_variation(
+        noderef: &Rc<RefCell<Self>>,
+    ) -> Vec<(Self::Move, f64)> {
+        let mut pv = Vec::new();
+        let mut current = noderef.clone();
+        loop {
+            let next = {
+                let node = current.borrow();
+                if let Some(player_move) = node.player_move() {
+                    pv.push((player_move.clone(), node.score()));
+                }
+                node.children()
+                    .iter()
+                    .filter(|ch| ch.borrow().nvisits() > 0)
+                    .max_by_key(|ch| ch.borrow().nvisits())
+                    .cloned()
+            };
+            match next {
+                Some(child) => current = child,
+                None => break,
+            }
+        }
+        pv
+    }
+
+    /// Number of plies in the deepest branch below `noderef`
+    fn depth(noderef: &Rc<RefCell<Self>>) -> usize {
+        let node = noderef.borrow();
+        1 + node.children().iter().map(Self::depth).max().unwrap_or(0)
+    }
+}
+
+impl<M: Clone> TreeNode for Node<M> {
+    type Move = M;
+
+    fn player_move(&self) -> Option<&M> {
+        self.player_move.as_ref()
+    }
+
+    fn nvisits(&self) -> u64 {
+        self.nvisits
+    }
+
+    fn score(&self) -> f64 {
+        Node::score(self)
+    }
+
+    fn children(&self) -> &[NodeRef<M>] {
+        &self.children
+    }
+}
+
+impl<M: Clone> Node<M> {
+    pub fn new(color: Cell, player_move: Option<M>) -> NodeRef<M> {
         let node = Node {
-            board,
             color,
             nwins: 0,
             nvisits: 0,
+            reward: 0.0,
             children: Vec::new(),
             parent: None,
             player_move,
@@ -37,7 +103,13 @@ impl Node {
         Rc::new(RefCell::new(node))
     }
 
-    pub fn selection(noderef: NodeRef, exploitation_value: f64) -> NodeRef {
+    /// Descends to a node to expand, playing the moves on the way on `game`
+    pub fn selection<G: Game<Move = M>>(
+        noderef: NodeRef<M>,
+        game: &mut G,
+        undos: &mut Vec<G::Undo>,
+        exploitation_value: f64,
+    ) -> NodeRef<M> {
         let mut selected = noderef;
         loop {
             let rc = selected.clone();
@@ -53,7 +125,7 @@ impl Node {
                 let child = ch.borrow();
                 let score = uct_score(
                     node.nvisits,
-                    child.nwins,
+                    child.reward,
                     child.nvisits,
                     exploitation_value,
                 );
@@ -62,28 +134,34 @@ impl Node {
                     selected = ch.clone();
                 }
             }
+            let child = selected.borrow();
+            undos.push(game.play(child.player_move.as_ref().unwrap()));
         }
         selected
     }
 
-    pub fn expansion(noderef: NodeRef) -> NodeRef {
+    /// Adds all children and descends into a random one, a finished
+    /// game becomes a leaf
+    pub fn expansion<G: Game<Move = M>>(
+        noderef: NodeRef<M>,
+        game: &mut G,
+        undos: &mut Vec<G::Undo>,
+    ) -> NodeRef<M> {
         let mut node = noderef.borrow_mut();
         assert!(node.children.is_empty());
-        let allowed = node.board.allowed_moves(node.color);
+        let moves = game.legal_moves();
 
-        if allowed.is_empty() {
+        if moves.is_empty() {
             node.leaf = true;
             noderef.clone()
         } else {
             let color = !node.color;
-            for player_move in allowed.iter() {
-                let board = node.board.with_move(&player_move, node.color);
-
+            for player_move in moves.iter() {
                 let child_node = Node {
                     color,
-                    board,
                     nwins: 0,
                     nvisits: 0,
+                    reward: 0.0,
                     parent: Some(Rc::downgrade(&noderef)),
                     children: Vec::new(),
                     player_move: Some(player_move.clone()),
@@ -94,29 +172,30 @@ impl Node {
                 node.children.push(noderc);
             }
 
-            let idx = random::<usize>() % node.children.len();
+            let idx = rng::thread_rng().gen_range(0, node.children.len());
+            undos.push(game.play(&moves[idx]));
             node.children[idx].clone()
         }
     }
 
-    pub fn simulate(&self, is_anti: bool) -> EndState {
-        Board::simauto(self.board, self.color, is_anti)
-    }
-
+    /// Counts the result for the side that moved into each node up to
+    /// the root, which is the side choosing between the node's siblings,
+    /// crediting that side with `reward` of its color
     pub fn back_propagate(
-        noderef: NodeRef,
-        winresult: EndState,
-        is_anti: bool,
+        noderef: NodeRef<M>,
+        result: EndState,
+        reward: impl Fn(Cell) -> f64,
     ) {
         let mut current = noderef;
         loop {
             {
                 let mut node = current.borrow_mut();
+                let credited = !node.color;
                 node.nvisits += 1;
-                let color = if is_anti { !node.color } else { node.color };
-                if winresult.won(color) {
+                if result.won(credited) {
                     node.nwins += 1;
                 }
+                node.reward += reward(credited);
             }
 
             let cloned = current.clone();
@@ -129,12 +208,12 @@ impl Node {
     }
 
     #[allow(dead_code)]
-    pub fn best_child(&self) -> NodeRef {
+    pub fn best_child(&self) -> NodeRef<M> {
         let mut best_node = self.children[0].clone();
         let mut best_score = 0f64;
         for ch in self.children.iter() {
             let child = ch.borrow();
-            let score = child.nwins as f64 / child.nvisits as f64;
+            let score = child.reward / child.nvisits as f64;
             if score > best_score {
                 best_score = score;
                 best_node = ch.clone();
@@ -144,7 +223,7 @@ impl Node {
     }
 
     #[allow(dead_code)]
-    pub fn repr_node(nr: &NodeRef, indent: usize) -> String {
+    pub fn repr_node(nr: &NodeRef<M>, indent: usize) -> String {
         let n = nr.borrow();
         let indstr = " ".repeat(indent * 2);
         let nv = n
@@ -155,16 +234,111 @@ impl Node {
             .join(",\n");
         format!(
             "{}Node({}/{}; [\n{}\n{}])",
-            indstr, n.nwins, n.nvisits, nv, indstr
+            indstr, n.reward, n.nvisits, nv, indstr
         )
     }
 
-    #[allow(dead_code)]
     pub fn score(&self) -> f64 {
         if self.nvisits == 0 {
             f64::MIN
         } else {
-            self.nwins as f64 / self.nvisits as f64
+            self.reward / self.nvisits as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        game::{
+            tests::{random_playout, TicTacToe},
+            Reversi,
+        },
+        position::Position,
+        variant::{self, Variant},
+    };
+
+    /// Move UCT plays from `s` after `playouts` random playouts
+    fn uct(s: &str, playouts: usize) -> usize {
+        let mut game = TicTacToe::parse(s);
+        let tree = Node::new(game.to_move, None);
+        let mut undos = Vec::new();
+        let mut rng = rng::thread_rng();
+        for _ in 0..playouts {
+            let selected =
+                Node::selection(tree.clone(), &mut game, &mut undos, 1.0);
+            let expanded = Node::expansion(selected, &mut game, &mut undos);
+            let result = random_playout(game.clone(), &mut rng);
+            Node::back_propagate(expanded, result, |color| {
+                variant::result_value(result, color)
+            });
+            while let Some(undo) = undos.pop() {
+                game.undo(undo);
+            }
+        }
+        assert_eq!(game.cells, TicTacToe::parse(s).cells);
+        let children = &tree.borrow().children;
+        let best = children.iter().max_by_key(|ch| ch.borrow().nvisits);
+        let player_move = best.unwrap().borrow().player_move;
+        player_move.unwrap()
+    }
+
+    #[test]
+    fn tic_tac_toe() {
+        // X wins at once, O blocks
+        assert_eq!(uct("XX_ OO_ ___", 2000), 2);
+        assert_eq!(uct("X_X _O_ ___", 2000), 1);
+        // O has to take an edge, a corner loses to a fork
+        assert_eq!(uct("X__ _O_ __X", 5000) % 2, 1);
+    }
+
+    #[test]
+    fn searches_through_passes() {
+        let s = "WWWW
+                 WWWW
+                 BBWW
+                 B___";
+        let mut game = Reversi {
+            position: Position::parse(s).unwrap(),
+            variant: Variant::Normal,
+        };
+        let tree = Node::new(Cell::Black, None);
+        let mut undos = Vec::new();
+        let mut rng = rng::thread_rng();
+        for _ in 0..100 {
+            let selected =
+                Node::selection(tree.clone(), &mut game, &mut undos, 1.0);
+            let expanded = Node::expansion(selected, &mut game, &mut undos);
+            let result = random_playout(game, &mut rng);
+            Node::back_propagate(expanded, result, |color| {
+                variant::result_value(result, color)
+            });
+            while let Some(undo) = undos.pop() {
+                game.undo(undo);
+            }
+        }
+        assert!(game.position.board == Position::parse(s).unwrap().board);
+        // Black's only move is the pass, white's replies are searched
+        let node = tree.borrow();
+        assert_eq!(node.children.len(), 1);
+        let pass = node.children[0].borrow();
+        assert!(pass.player_move.as_ref().unwrap().is_none());
+        assert_eq!(pass.color, Cell::White);
+        assert!(!pass.leaf && !pass.children.is_empty());
+    }
+
+    #[test]
+    fn tie_is_half_a_win() {
+        // White moved into the node
+        let tree = Node::<usize>::new(Cell::Black, None);
+        for &result in &[EndState::Tie, EndState::WhiteWon] {
+            Node::back_propagate(tree.clone(), result, |color| {
+                variant::result_value(result, color)
+            });
         }
+        let node = tree.borrow();
+        assert_eq!((node.nwins, node.nvisits), (1, 2));
+        assert_eq!(node.score(), 0.75);
     }
 }
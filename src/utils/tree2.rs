@@ -1,42 +1,211 @@
-use super::*;
-use rand::random;
+use super::{
+    board::Board,
+    game::{Game, Reversi},
+    negamax::Negamax,
+    rollout::Rollout,
+    sev::tile_weight,
+    tree::TreeNode,
+    *,
+};
+use rand::Rng;
 use std::{
     cell::RefCell,
     rc::{Rc, Weak},
 };
 
-pub struct Node {
-    pub board: Board,
+/// Node of the trees of `mcts_minimax` and `mcts_rave` over any game with
+/// moves of type `M`
+pub struct Node<M> {
     pub color: Cell,
-    pub children: Vec<Rc<RefCell<Node>>>,
-    pub parent: Option<Weak<RefCell<Node>>>,
-    pub player_move: Option<PlayerMove>,
+    pub children: Vec<NodeRef<M>>,
+    pub parent: Option<Weak<RefCell<Node<M>>>>,
+    pub player_move: Option<M>,
     pub leaf: bool,
 
     pub nwins: u64,
     pub nvisits: u64,
+    /// Sum of the playout rewards, a won playout is worth 1
+    pub reward: f64,
+
+    /// All-moves-as-first statistics of this node's move
+    pub amaf_reward: f64,
+    pub amaf_visits: u64,
+
+    /// Probability of this node's move being good before any playout,
+    /// siblings are sorted by it
+    pub prior: f64,
+
+    /// Result of the game under perfect play once it's proven
+    pub proof: Option<EndState>,
+
+    /// Heuristic value for the same side as `reward`, from 0 to 1,
+    /// backed up from the children like minimax
+    pub minimax: f64,
+}
+
+pub type NodeRef<M> = Rc<RefCell<Node<M>>>;
+
+/// What the trees know about a game beyond its rules
+pub trait Heuristics: Game {
+    /// Static evaluation for the side to move
+    fn evaluate(&self) -> Score;
+
+    /// How good a move looks to the side to move, on the scale of
+    /// `PRIOR_TEMPERATURE`
+    fn move_weight(&self, player_move: &Self::Move) -> f64;
+}
+
+/// Moves the AMAF statistics can tell apart
+pub trait AmafSlot {
+    /// Slot below 128 shared by the moves to the same place, none for
+    /// a move without a place like a pass
+    fn amaf_slot(&self) -> Option<usize>;
+}
+
+impl Heuristics for Reversi {
+    fn evaluate(&self) -> Score {
+        let position = &self.position;
+        self.variant.evaluate(&position.board, position.to_move)
+    }
+
+    fn move_weight(&self, player_move: &Self::Move) -> f64 {
+        match player_move {
+            Some(player_move) => {
+                let weight = tile_weight(&self.position.board, player_move.0);
+                (self.variant.sign() * weight) as f64
+            }
+            None => 0.0,
+        }
+    }
+}
+
+impl AmafSlot for Option<PlayerMove> {
+    fn amaf_slot(&self) -> Option<usize> {
+        self.as_ref().map(|player_move| player_move.0.usize())
+    }
+}
+
+/// Spread of tile weights that makes one move `e` times likelier
+const PRIOR_TEMPERATURE: f64 = 50.0;
+
+/// How move priors steer the search, both are off by default
+#[derive(Clone, Copy, Debug)]
+pub struct Priors {
+    /// Weight of the prior in selection, fading with visits
+    pub bias: f64,
+    /// Whether children are opened one by one in order of prior
+    pub widening: bool,
+}
+
+impl Priors {
+    pub fn from_args(matches: &ArgMatches) -> Self {
+        let bias = matches
+            .value_of("prior_bias")
+            .map(|it| it.parse::<f64>().unwrap())
+            .unwrap();
+        let widening = matches.is_present("widening");
+        Self { bias, widening }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.bias > 0.0 || self.widening
+    }
+
+    /// Progressive bias term of a child's selection score
+    fn bias<M>(&self, child: &Node<M>) -> f64 {
+        self.bias * child.prior / (child.nvisits + 1) as f64
+    }
+
+    /// Unproven children that can be selected, one more opens whenever
+    /// the square root of the visits grows by one
+    fn widened<'a, M>(
+        &self,
+        node: &'a Node<M>,
+    ) -> impl Iterator<Item = &'a NodeRef<M>> {
+        let nopen = if self.widening {
+            1 + (node.nvisits as f64).sqrt() as usize
+        } else {
+            usize::MAX
+        };
+        node.children
+            .iter()
+            .filter(|ch| ch.borrow().proof.is_none())
+            .take(nopen)
+    }
 }
 
-pub type NodeRef = Rc<RefCell<Node>>;
+/// Evaluation difference that makes a minimax value of about 0.73
+const SEV_SCALE: f64 = 400.0;
+
+/// Depth of the alpha-beta search that checks a node's children
+const CHECK_DEPTH: usize = 2;
 
-impl Node {
-    pub fn new(
-        board: Board,
-        color: Cell,
-        player_move: Option<PlayerMove>,
-    ) -> NodeRef {
+/// Implicit minimax backups: heuristic values of the nodes are backed up
+/// like minimax and mixed into their win ratios
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Minimax {
+    /// Share of the minimax value in the node scores, 0 turns it off
+    pub weight: f64,
+    /// Visits after which the children of a node are checked with
+    /// a shallow alpha-beta search instead of the static evaluation
+    pub threshold: u64,
+}
+
+impl Minimax {
+    pub fn from_args(matches: &ArgMatches) -> Self {
+        let weight = matches
+            .value_of("minimax_weight")
+            .map(|it| it.parse::<f64>().unwrap())
+            .unwrap();
+        let threshold = matches
+            .value_of("minimax_threshold")
+            .map(|it| it.parse::<u64>().unwrap())
+            .unwrap();
+        Self { weight, threshold }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.weight > 0.0
+    }
+}
+
+/// Minimax value of `game` for the side that moved into it
+fn heuristic_value<G: Heuristics>(game: &mut G, depth: usize) -> f64 {
+    let mut negamax = Negamax::new(G::evaluate);
+    let score = -negamax.search(game, depth, -Score::MAX, Score::MAX).0;
+    1.0 / (1.0 + (-score as f64 / SEV_SCALE).exp())
+}
+
+/// Softmax over the weights of the moves, from the mover's side
+fn move_priors<G: Heuristics>(game: &G, moves: &[G::Move]) -> Vec<f64> {
+    let weights = moves
+        .iter()
+        .map(|m| game.move_weight(m) / PRIOR_TEMPERATURE)
+        .collect::<Vec<_>>();
+    let max = weights.iter().cloned().fold(f64::MIN, f64::max);
+    let exps = weights.iter().map(|w| (w - max).exp()).collect::<Vec<_>>();
+    let sum = exps.iter().sum::<f64>();
+    exps.iter().map(|e| e / sum).collect()
+}
+
+impl<M: Clone> Node<M> {
+    pub fn new(color: Cell, player_move: Option<M>) -> NodeRef<M> {
         let node = Node {
-            board,
             color,
             nwins: 0,
             nvisits: 0,
+            reward: 0.0,
+            amaf_reward: 0.0,
+            amaf_visits: 0,
+            prior: 1.0,
+            proof: None,
+            minimax: 0.5,
             children: Vec::new(),
             parent: None,
             player_move,
             leaf: false,
         };
-        let rc = Rc::new(RefCell::new(node));
-        rc
+        Rc::new(RefCell::new(node))
     }
 
     //     pub fn selection(noderef: NodeRef, my_color: Cell) {
@@ -102,18 +271,37 @@ impl Node {
     //     }
     // }
 
-    pub fn selection(noderef: NodeRef, exploitation_value: f64) -> NodeRef {
-        let root_color = {
+    /// Descends to a node to expand, playing the moves on the way on `game`
+    pub fn selection<G: Heuristics<Move = M>>(
+        noderef: NodeRef<M>,
+        game: &mut G,
+        undos: &mut Vec<G::Undo>,
+        exploitation_value: f64,
+        priors: Priors,
+        minimax: Minimax,
+    ) -> NodeRef<M> {
+        let _root_color = {
             let bor = noderef.borrow();
             bor.color
         };
         let mut selected = noderef;
 
         loop {
+            if minimax.is_on() {
+                let check = {
+                    let node = selected.borrow();
+                    node.nvisits == minimax.threshold
+                        && !node.children.is_empty()
+                };
+                if check {
+                    Node::check_children(&selected, game);
+                }
+            }
+
             let rc = selected.clone();
             let node = rc.borrow();
 
-            if node.children.is_empty() || node.leaf {
+            if node.children.is_empty() || node.leaf || node.proof.is_some() {
                 break;
             }
 
@@ -152,41 +340,69 @@ impl Node {
 
             let mut max_score = f64::MIN;
 
-            for ch in node.children.iter() {
+            for ch in priors.widened(&node) {
                 let child = ch.borrow();
                 let score = uct_score(
                     node.nvisits,
-                    child.nwins,
+                    child.mixed_score(minimax.weight) * child.nvisits as f64,
                     child.nvisits,
                     exploitation_value,
-                );
+                ) + priors.bias(&child);
                 if score > max_score {
                     max_score = score;
                     selected = ch.clone();
                 }
             }
+            let child = selected.borrow();
+            undos.push(game.play(child.player_move.as_ref().unwrap()));
         }
         selected
     }
 
-    pub fn expansion(noderef: NodeRef, _is_anti: bool) -> NodeRef {
+    /// Adds all children sorted by prior and descends into a random one,
+    /// or into the likeliest one when priors are on
+    pub fn expansion<G: Heuristics<Move = M>>(
+        noderef: NodeRef<M>,
+        game: &mut G,
+        undos: &mut Vec<G::Undo>,
+        priors: Priors,
+        minimax: Minimax,
+    ) -> NodeRef<M> {
         let mut node = noderef.borrow_mut();
         assert!(node.children.is_empty());
-        let allowed = node.board.allowed_moves(node.color);
+        let legal = game.legal_moves();
 
-        if allowed.is_empty() {
+        if legal.is_empty() {
             node.leaf = true;
+            let result = game.result();
+            if result.is_over() {
+                node.proof = Some(result);
+            }
             noderef.clone()
         } else {
             let color = !node.color;
-            for player_move in allowed.iter() {
-                let board = node.board.with_move(&player_move, node.color);
-
+            let weights = move_priors(game, &legal);
+            let mut moves = legal.into_iter().zip(weights).collect::<Vec<_>>();
+            moves.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+            for (player_move, prior) in moves.iter() {
+                let minimax = if minimax.is_on() {
+                    let undo = game.play(player_move);
+                    let value = heuristic_value(game, 0);
+                    game.undo(undo);
+                    value
+                } else {
+                    0.5
+                };
                 let child_node = Node {
                     color,
-                    board,
                     nwins: 0,
                     nvisits: 0,
+                    reward: 0.0,
+                    amaf_reward: 0.0,
+                    amaf_visits: 0,
+                    prior: *prior,
+                    proof: None,
+                    minimax,
                     parent: Some(Rc::downgrade(&noderef)),
                     children: Vec::new(),
                     player_move: Some(player_move.clone()),
@@ -197,43 +413,27 @@ impl Node {
                 node.children.push(rc);
             }
 
-            let idx = random::<usize>() % node.children.len();
+            let idx = if priors.is_on() {
+                0
+            } else {
+                rng::thread_rng().gen_range(0, node.children.len())
+            };
+            undos.push(game.play(&moves[idx].0));
             node.children[idx].clone()
         }
     }
 
-    pub fn simulate(
-        noderef: NodeRef,
-        is_anti: bool,
-        is_depth_even: bool,
-        bot_color: Cell,
-    ) -> EndState {
-        let node = noderef.borrow();
-        Board::sim_with_sev(
-            node.board,
-            node.color,
-            is_anti,
-            is_depth_even,
-            bot_color,
-        )
-        // Board::simauto(node.board, node.color, is_anti)
-    }
-
+    /// Counts the result for the side that moved into each node up to
+    /// the root, which is the side choosing between the node's siblings,
+    /// crediting that side with `reward` of its color
     pub fn back_propagate(
-        noderef: NodeRef,
-        winresult: EndState,
-        is_anti: bool,
+        noderef: NodeRef<M>,
+        result: EndState,
+        reward: impl Fn(Cell) -> f64,
     ) {
-        let mut current = noderef;
+        let mut current = noderef.clone();
         loop {
-            {
-                let mut node = current.borrow_mut();
-                node.nvisits += 1;
-                let color = if is_anti { !node.color } else { node.color };
-                if winresult.won(color) {
-                    node.nwins += 1;
-                }
-            }
+            current.borrow_mut().record(result, &reward);
 
             let cloned = current.clone();
             if let Some(parent) = &cloned.borrow().parent {
@@ -242,6 +442,192 @@ impl Node {
                 break;
             };
         }
+        Node::prove(noderef);
+    }
+
+    /// Passes a proven result of `noderef` up: a parent is proven when
+    /// the side to move has a proven win or every child is proven
+    pub fn prove(noderef: NodeRef<M>) {
+        let mut current = noderef;
+        while current.borrow().proof.is_some() {
+            let parent = match &current.borrow().parent {
+                Some(parent) => parent.upgrade().unwrap(),
+                None => break,
+            };
+            let proof = Node::solve(&parent.borrow());
+            if proof.is_none() {
+                break;
+            }
+            parent.borrow_mut().proof = proof;
+            current = parent;
+        }
+    }
+
+    /// Replaces the static values of the children with a shallow
+    /// alpha-beta search from `game`, the position of `noderef`
+    pub fn check_children<G: Heuristics<Move = M>>(
+        noderef: &NodeRef<M>,
+        game: &mut G,
+    ) {
+        for ch in noderef.borrow().children.iter() {
+            let mut child = ch.borrow_mut();
+            let undo = game.play(child.player_move.as_ref().unwrap());
+            child.minimax = heuristic_value(game, CHECK_DEPTH);
+            game.undo(undo);
+        }
+        Node::back_up_minimax(noderef.clone());
+    }
+
+    /// Updates the minimax values from `noderef` up to the root, proven
+    /// children count with their exact value
+    pub fn back_up_minimax(noderef: NodeRef<M>) {
+        let mut current = Some(noderef);
+        while let Some(noderef) = current {
+            let mut node = noderef.borrow_mut();
+            if !node.children.is_empty() {
+                let best = node
+                    .children
+                    .iter()
+                    .map(|ch| {
+                        let child = ch.borrow();
+                        child.proven_score(node.color).unwrap_or(child.minimax)
+                    })
+                    .fold(f64::MIN, f64::max);
+                node.minimax = 1.0 - best;
+            }
+            current = node.parent.as_ref().map(|p| p.upgrade().unwrap());
+        }
+    }
+
+    /// Win ratio with `weight` of it taken from the minimax value
+    pub fn mixed_score(&self, weight: f64) -> f64 {
+        (1.0 - weight) * self.reward / self.nvisits as f64
+            + weight * self.minimax
+    }
+
+    /// Best proven result for the side to move, if it's known
+    fn solve(node: &Node<M>) -> Option<EndState> {
+        let mut best = None;
+        let mut unproven = false;
+        for ch in node.children.iter() {
+            match ch.borrow().proof {
+                Some(result) if result.won(node.color) => return Some(result),
+                Some(EndState::Tie) => best = Some(EndState::Tie),
+                Some(result) => best = best.or(Some(result)),
+                None => unproven = true,
+            }
+        }
+        best.filter(|_| !unproven)
+    }
+
+    /// Win ratio for `color` of a proven node, 0.5 for a tie
+    pub fn proven_score(&self, color: Cell) -> Option<f64> {
+        self.proof
+            .map(|result| variant::result_value(result, color))
+    }
+
+    /// Same as `selection`, with each child's win ratio blended with its
+    /// AMAF ratio; the AMAF weight fades as `sqrt(k / (3n + k))`
+    pub fn selection_rave<G: Game<Move = M>>(
+        noderef: NodeRef<M>,
+        game: &mut G,
+        undos: &mut Vec<G::Undo>,
+        exploitation_value: f64,
+        rave_k: f64,
+        priors: Priors,
+    ) -> NodeRef<M> {
+        let mut selected = noderef;
+        loop {
+            let rc = selected.clone();
+            let node = rc.borrow();
+            if node.children.is_empty() || node.leaf || node.proof.is_some() {
+                break;
+            }
+
+            let mut max_score = f64::MIN;
+            for ch in priors.widened(&node) {
+                let child = ch.borrow();
+                let score = if child.nvisits == 0 {
+                    f64::MAX
+                } else {
+                    let nvisits = child.nvisits as f64;
+                    let beta = (rave_k / (3.0 * nvisits + rave_k)).sqrt();
+                    let amaf = if child.amaf_visits == 0 {
+                        0.0
+                    } else {
+                        child.amaf_reward / child.amaf_visits as f64
+                    };
+                    let exploration = exploitation_value
+                        * ((node.nvisits as f64).ln() / nvisits).sqrt();
+                    (1.0 - beta) * child.score()
+                        + beta * amaf
+                        + exploration
+                        + priors.bias(&child)
+                };
+                if score > max_score {
+                    max_score = score;
+                    selected = ch.clone();
+                }
+            }
+            let child = selected.borrow();
+            undos.push(game.play(child.player_move.as_ref().unwrap()));
+        }
+        selected
+    }
+
+    /// Backs up the result like `back_propagate` and updates the AMAF
+    /// statistics of every child whose move the side to move played
+    /// later on, in the tree or in the playout; `played` has the slots
+    /// of the playout's moves
+    pub fn back_propagate_rave(
+        noderef: NodeRef<M>,
+        result: EndState,
+        reward: impl Fn(Cell) -> f64,
+        played: impl IntoIterator<Item = (usize, Cell)>,
+    ) where
+        M: AmafSlot,
+    {
+        // Slots of the moves played below the current node, by color
+        let mut below = [0u128; 2];
+        let side = |color| (color == Cell::White) as usize;
+        for (slot, color) in played {
+            below[side(color)] |= 1 << slot;
+        }
+        let is_set = |bits: u128, slot: Option<usize>| {
+            slot.is_some_and(|slot| bits & 1 << slot != 0)
+        };
+
+        let mut current = noderef.clone();
+        loop {
+            {
+                let mut node = current.borrow_mut();
+                node.record(result, &reward);
+                let moved = below[side(node.color)];
+                for ch in node.children.iter() {
+                    let mut child = ch.borrow_mut();
+                    let slot = child.player_move.as_ref().unwrap().amaf_slot();
+                    if is_set(moved, slot) {
+                        child.amaf_visits += 1;
+                        child.amaf_reward += reward(node.color);
+                    }
+                }
+            }
+
+            let cloned = current.clone();
+            let node = cloned.borrow();
+            match &node.parent {
+                Some(parent) => {
+                    let parent = parent.upgrade().unwrap();
+                    let slot = node.player_move.as_ref().unwrap().amaf_slot();
+                    if let Some(slot) = slot {
+                        below[side(parent.borrow().color)] |= 1 << slot;
+                    }
+                    current = parent;
+                }
+                None => break,
+            }
+        }
+        Node::prove(noderef);
     }
 
     // pub fn calc_minimax(noderef: NodeRef, my_color: Cell) {
@@ -274,7 +660,7 @@ impl Node {
     // }
 
     #[allow(dead_code)]
-    pub fn best_child(&self) -> NodeRef {
+    pub fn best_child(&self) -> NodeRef<M> {
         let mut best_node = self.children[0].clone();
         let mut best_score = 0f64;
         for ch in self.children.iter() {
@@ -289,7 +675,7 @@ impl Node {
     }
 
     #[allow(dead_code)]
-    pub fn repr_node(nr: &NodeRef, indent: usize) -> String {
+    pub fn repr_node(nr: &NodeRef<M>, indent: usize) -> String {
         let n = nr.borrow();
         let indstr = " ".repeat(indent * 2);
         let nv = n
@@ -304,12 +690,319 @@ impl Node {
         )
     }
 
-    #[allow(dead_code)]
+    /// Average reward, the win ratio while margins are off
     pub fn score(&self) -> f64 {
         if self.nvisits == 0 {
             f64::MIN
         } else {
-            self.nwins as f64 / self.nvisits as f64
+            self.reward / self.nvisits as f64
+        }
+    }
+
+    /// Counts a playout through this node for the side that moved into it
+    fn record(&mut self, result: EndState, reward: impl Fn(Cell) -> f64) {
+        let credited = !self.color;
+        self.nvisits += 1;
+        if result.won(credited) {
+            self.nwins += 1;
+        }
+        self.reward += reward(credited);
+    }
+}
+
+impl Node<Option<PlayerMove>> {
+    /// Plays out from `board`, the position of `noderef`
+    pub fn simulate(
+        noderef: NodeRef<Option<PlayerMove>>,
+        board: &Board,
+        variant: Variant,
+        rollout: &Rollout,
+    ) -> Outcome {
+        let node = noderef.borrow();
+        rollout.play(
+            *board,
+            node.color,
+            variant,
+            &mut rng::thread_rng(),
+            &mut Vec::new(),
+        )
+        // Board::simauto(node.board, node.color, is_anti)
+    }
+}
+
+impl<M: Clone> TreeNode for Node<M> {
+    type Move = M;
+
+    fn player_move(&self) -> Option<&M> {
+        self.player_move.as_ref()
+    }
+
+    fn nvisits(&self) -> u64 {
+        self.nvisits
+    }
+
+    fn score(&self) -> f64 {
+        Node::score(self)
+    }
+
+    fn children(&self) -> &[NodeRef<M>] {
+        &self.children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        game::tests::TicTacToe, rollout::Policy, variant::tests::exact,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    impl Heuristics for TicTacToe {
+        fn evaluate(&self) -> Score {
+            0
         }
+
+        fn move_weight(&self, &i: &usize) -> f64 {
+            // The centre, then the corners
+            [1.0, 0.0, 1.0, 0.0, 2.0, 0.0, 1.0, 0.0, 1.0][i]
+        }
+    }
+
+    impl AmafSlot for usize {
+        fn amaf_slot(&self) -> Option<usize> {
+            Some(*self)
+        }
+    }
+
+    /// Move RAVE plays from `s` after `playouts` random playouts, and
+    /// whether the root got proven
+    fn rave(s: &str, playouts: usize) -> (usize, Option<EndState>) {
+        let mut game = TicTacToe::parse(s);
+        let tree = Node::new(game.to_move, None);
+        let priors = Priors {
+            bias: 1.0,
+            widening: false,
+        };
+        let mut undos = Vec::new();
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..playouts {
+            if tree.borrow().proof.is_some() {
+                break;
+            }
+            let selected = Node::selection_rave(
+                tree.clone(),
+                &mut game,
+                &mut undos,
+                1.0,
+                100.0,
+                priors,
+            );
+            let expanded = Node::expansion(
+                selected,
+                &mut game,
+                &mut undos,
+                priors,
+                Minimax::default(),
+            );
+            // Random playout that keeps its moves for the AMAF statistics
+            let mut playout = game.clone();
+            let mut played = Vec::new();
+            loop {
+                let moves = playout.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                let i = moves[rng.gen_range(0, moves.len())];
+                played.push((i, playout.to_move()));
+                playout.play(&i);
+            }
+            let result = playout.result();
+            Node::back_propagate_rave(
+                expanded,
+                result,
+                |color| variant::result_value(result, color),
+                played,
+            );
+            while let Some(undo) = undos.pop() {
+                game.undo(undo);
+            }
+        }
+        assert_eq!(game.cells, TicTacToe::parse(s).cells);
+        let best = Node::principal_variation(&tree)[0].0;
+        let proof = tree.borrow().proof;
+        (best, proof)
+    }
+
+    #[test]
+    fn tic_tac_toe() {
+        // X wins at once and the tree proves it
+        assert_eq!(rave("XX_ OO_ ___", 500), (2, Some(EndState::BlackWon)));
+        // O has to block
+        assert_eq!(rave("X_X _O_ ___", 2000).0, 1);
+    }
+
+    #[test]
+    fn expansion_follows_priors() {
+        let s = "_WB_____
+                 ________
+                 ________
+                 ___WB___
+                 ___BW___
+                 ________
+                 ________
+                 ________";
+        let position = Position::parse(s).unwrap();
+        let corner = Point::from_xy(0, 0);
+        let priors = Priors {
+            bias: 1.0,
+            widening: true,
+        };
+        for &variant in [Variant::Normal, Variant::Anti].iter() {
+            let mut game = Reversi { position, variant };
+            let root = Node::new(Cell::Black, None);
+            let mut undos = Vec::new();
+            let child = Node::expansion(
+                root.clone(),
+                &mut game,
+                &mut undos,
+                priors,
+                Minimax::default(),
+            );
+            let root = root.borrow();
+            let points = root
+                .children
+                .iter()
+                .map(|ch| ch.borrow().player_move.clone().unwrap().unwrap().0)
+                .collect::<Vec<_>>();
+            // Taking the corner is best in reversi and worst in anti
+            let expected = match variant {
+                Variant::Normal => 0,
+                Variant::Anti => points.len() - 1,
+            };
+            assert_eq!(
+                points.iter().position(|&p| p == corner),
+                Some(expected)
+            );
+            assert!(Rc::ptr_eq(&child, &root.children[0]));
+            assert_eq!(priors.widened(&root).count(), 1);
+            let total = root.children.iter().map(|ch| ch.borrow().prior);
+            assert!((total.sum::<f64>() - 1.0).abs() < 1e-9);
+            game.undo(undos.pop().unwrap());
+            assert!(game.position.board == position.board);
+        }
+    }
+
+    #[test]
+    fn solver_matches_exhaustive_search() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let priors = Priors {
+            bias: 0.0,
+            widening: false,
+        };
+        let rollout = Rollout {
+            policy: Policy::Random,
+            cutoff: None,
+        };
+        let mut nproven = 0;
+        for _ in 0..20 {
+            let mut pos = Position::initial(4, &[]).unwrap();
+            pos.play_random(7, &mut rng);
+            let variant = if rng.gen() {
+                Variant::Anti
+            } else {
+                Variant::Normal
+            };
+            let mut game = Reversi {
+                position: pos,
+                variant,
+            };
+            let tree = Node::new(pos.to_move, None);
+            let mut undos = Vec::new();
+            for _ in 0..2000 {
+                if tree.borrow().proof.is_some() {
+                    break;
+                }
+                let selected = Node::selection(
+                    tree.clone(),
+                    &mut game,
+                    &mut undos,
+                    1.4,
+                    priors,
+                    Minimax::default(),
+                );
+                let expanded = Node::expansion(
+                    selected,
+                    &mut game,
+                    &mut undos,
+                    priors,
+                    Minimax::default(),
+                );
+                let outcome = rollout.play(
+                    game.position.board,
+                    game.to_move(),
+                    variant,
+                    &mut rng,
+                    &mut Vec::new(),
+                );
+                Node::back_propagate(expanded, outcome.result, |color| {
+                    variant::result_value(outcome.result, color)
+                });
+                while let Some(undo) = undos.pop() {
+                    game.undo(undo);
+                }
+            }
+            let proof = tree.borrow().proof;
+            if let Some(proof) = proof {
+                let board = &mut game.position.board;
+                assert_eq!(proof, exact(board, pos.to_move, variant));
+                nproven += 1;
+            }
+        }
+        assert!(nproven >= 5, "only {} positions proven", nproven);
+    }
+
+    #[test]
+    fn minimax_backs_up() {
+        let initial = Position::initial(8, &[]).unwrap();
+        let mut game = Reversi {
+            position: initial,
+            variant: Variant::Normal,
+        };
+        let priors = Priors {
+            bias: 0.0,
+            widening: false,
+        };
+        let minimax = Minimax {
+            weight: 0.5,
+            threshold: 0,
+        };
+        let root = Node::new(Cell::Black, None);
+        let mut undos = Vec::new();
+        let child = Node::expansion(
+            root.clone(),
+            &mut game,
+            &mut undos,
+            priors,
+            minimax,
+        );
+        game.undo(undos.pop().unwrap());
+        Node::back_up_minimax(child);
+        let values = |root: &NodeRef<Option<PlayerMove>>| {
+            let root = root.borrow();
+            let children = root.children.iter();
+            children.map(|ch| ch.borrow().minimax).collect::<Vec<_>>()
+        };
+        // The four openings are symmetric
+        let static_values = values(&root);
+        assert!(static_values.iter().all(|&v| v == static_values[0]));
+        assert_eq!(root.borrow().minimax, 1.0 - static_values[0]);
+
+        Node::check_children(&root, &mut game);
+        let checked = values(&root);
+        assert_ne!(checked, static_values);
+        let best = checked.iter().cloned().fold(f64::MIN, f64::max);
+        assert_eq!(root.borrow().minimax, 1.0 - best);
+        assert!(game.position.board == initial.board);
     }
 }
@@ -0,0 +1,166 @@
+use super::{sev::sev3, *};
+
+/// Which side the disc count favours. Game results, playout rewards and
+/// evaluations are all scored through it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    /// More discs win
+    Normal,
+    /// Fewer discs win
+    Anti,
+}
+
+impl Variant {
+    pub fn from_args(matches: &ArgMatches) -> Self {
+        if matches.is_present("no_anti") {
+            Variant::Normal
+        } else {
+            Variant::Anti
+        }
+    }
+
+    /// 1 when discs are good to own, -1 when they're not
+    pub fn sign(self) -> Score {
+        match self {
+            Variant::Normal => 1,
+            Variant::Anti => -1,
+        }
+    }
+
+    /// Result of a finished game on `board`, equal counts are a tie
+    pub fn result(self, board: &Board) -> EndState {
+        let margin = (board.count(Cell::Black) as Score
+            - board.count(Cell::White) as Score)
+            * self.sign();
+        match margin {
+            m if m > 0 => EndState::BlackWon,
+            m if m < 0 => EndState::WhiteWon,
+            _ => EndState::Tie,
+        }
+    }
+
+    /// Static evaluation of `board` for `color`, the opponent's is the
+    /// same with the other sign
+    pub fn evaluate(self, board: &Board, color: Cell) -> Score {
+        // The terms of empty tiles don't depend on the side, this cancels them
+        let score =
+            (sev3(board, color, false) - sev3(board, !color, false)) / 2;
+        score * self.sign()
+    }
+}
+
+/// What a result is worth to `color`, a tie is half a win
+pub fn result_value(result: EndState, color: Cell) -> f64 {
+    match result {
+        EndState::Tie => 0.5,
+        r if r.won(color) => 1.0,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::utils::{config::BotConfig, position::Position};
+
+    /// Black to move with six empty tiles left: only F4 wins regular
+    /// reversi and only F5 wins anti reversi
+    const ENDGAME: &str = "_BBBBB
+                           _BBWBB
+                           WBBBWB
+                           WWBWW_
+                           WWWWW_
+                           WWWW__";
+
+    const BOTS: [&str; 5] = [
+        "--bot-impl minimax --max-depth 6",
+        "--bot-impl mcts_basic --playouts 3000",
+        "--bot-impl mcts --playouts 3000",
+        "--bot-impl mcts_minimax --playouts 3000",
+        "--bot-impl mcts_rave --playouts 3000",
+    ];
+
+    /// Result under perfect play by exhaustive search
+    pub fn exact(board: &mut Board, color: Cell, variant: Variant) -> EndState {
+        let allowed = board.allowed_moves(color);
+        let result = wincheck(board, &allowed, variant, color);
+        if result.is_over() {
+            return result;
+        }
+        if allowed.is_empty() {
+            return exact(board, !color, variant);
+        }
+        let mut best = EndState::Unknown;
+        for player_move in allowed.iter() {
+            let undo = board.make_move(player_move, color);
+            let result = exact(board, !color, variant);
+            board.unmake_move(undo);
+            if result.won(color) {
+                return result;
+            }
+            if best != EndState::Tie {
+                best = result;
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn results() {
+        let s = "BBBW
+                 BBBW
+                 BBBW
+                 BBBW";
+        let position = Position::parse(s).unwrap();
+        assert_eq!(Variant::Normal.result(&position.board), EndState::BlackWon);
+        assert_eq!(Variant::Anti.result(&position.board), EndState::WhiteWon);
+        let score = Variant::Anti.evaluate(&position.board, Cell::Black);
+        assert_eq!(
+            score,
+            -Variant::Anti.evaluate(&position.board, Cell::White)
+        );
+        assert_eq!(
+            score,
+            -Variant::Normal.evaluate(&position.board, Cell::Black)
+        );
+    }
+
+    #[test]
+    fn bots_play_the_variant() {
+        let position = Position::parse(ENDGAME).unwrap();
+        for &(variant, winning) in
+            [(Variant::Normal, "F4"), (Variant::Anti, "F5")].iter()
+        {
+            // The position really has one winning move
+            let mut board = position.board;
+            for player_move in board.allowed_moves(Cell::Black).iter() {
+                let undo = board.make_move(player_move, Cell::Black);
+                let won =
+                    exact(&mut board, Cell::White, variant).won(Cell::Black);
+                board.unmake_move(undo);
+                assert_eq!(won, player_move.0.to_ab() == winning);
+            }
+
+            for spec in BOTS.iter() {
+                let mut args = vec!["reversi_bot", "-t", "0", "--seed", "1"];
+                args.extend(spec.split_whitespace());
+                if variant == Variant::Normal {
+                    args.push("--no-anti");
+                }
+                let matches = app().get_matches_from(args);
+                rng::seed_from_args(&matches);
+                let config = BotConfig::from_args(&matches).unwrap();
+                let (player_move, _) = rng::tests::repeatable(|| {
+                    create_bot(&config, position, Cell::Black).run_ai()
+                });
+                assert_eq!(
+                    player_move.0.to_ab(),
+                    winning,
+                    "{} in {:?}",
+                    spec,
+                    variant
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use super::{point::MAX_CELLS, position::Position, *};
+
+/// One key per tile for each of black, white and black hole
+static KEYS: [[u64; 3]; MAX_CELLS] = keys();
+
+/// Key of white being the side to move
+const WHITE_TO_MOVE: u64 = splitmix64(0x9c2f_83b1_e75a_4d06);
+
+/// Keys are fixed at compile time so hashes are the same across runs
+const fn keys() -> [[u64; 3]; MAX_CELLS] {
+    let mut keys = [[0; 3]; MAX_CELLS];
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut i = 0;
+    while i < MAX_CELLS * 3 {
+        state = splitmix64(state);
+        keys[i / 3][i % 3] = state;
+        i += 1;
+    }
+    keys
+}
+
+const fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Key of `cell` standing on `p`, empty tiles don't contribute
+#[inline]
+pub fn key(p: Point, cell: Cell) -> u64 {
+    let keys = &KEYS[p.usize()];
+    match cell {
+        Cell::Empty => 0,
+        Cell::Black => keys[0],
+        Cell::White => keys[1],
+        Cell::BlackHole => keys[2],
+    }
+}
+
+/// Change of the hash when the disc on `p` is flipped
+#[inline]
+pub fn flip_key(p: Point) -> u64 {
+    let keys = &KEYS[p.usize()];
+    keys[0] ^ keys[1]
+}
+
+/// Key of `color` being the side to move
+#[inline]
+pub fn side_key(color: Cell) -> u64 {
+    match color {
+        Cell::White => WHITE_TO_MOVE,
+        _ => 0,
+    }
+}
+
+/// Hash of the cells and the side to move computed from scratch, what
+/// `Position::hash` keeps up to date move by move
+pub fn hash(position: &Position) -> u64 {
+    let board = &position.board;
+    board.points().fold(side_key(position.to_move), |hash, p| {
+        hash ^ key(p, board.at(p))
+    })
+}
@@ -0,0 +1,128 @@
+use reversi_bot::{
+    eval, perft, Board, BotConfig, Cell, EndState, Engine, Game, Limits, Point,
+    Position, Reversi, Variant,
+};
+use std::time::Duration;
+
+fn playouts(n: u64) -> Limits {
+    Limits {
+        time: None,
+        playouts: Some(n),
+    }
+}
+
+#[test]
+fn move_generation() {
+    let mut board = Board::new(8, &[]).unwrap();
+    let moves = board.allowed_moves(Cell::Black);
+    let mut points = moves.iter().map(|m| m.0.to_ab()).collect::<Vec<_>>();
+    points.sort();
+    assert_eq!(points, ["C4", "D3", "E6", "F5"]);
+    assert_eq!(perft(&mut board, Cell::Black, 5), 1396);
+
+    // A black hole blocks the ray through it
+    let hole = Point::from_ab("C4").unwrap();
+    let board = Board::new(8, &[hole]).unwrap();
+    assert_eq!(board.allowed_moves(Cell::Black).len(), 3);
+}
+
+#[test]
+fn evaluation_favours_corners() {
+    let board = Board::new(8, &[]).unwrap();
+    assert_eq!(eval::static_eval(&board, Cell::Black), 0);
+    let corner = Point::from_ab("A1").unwrap();
+    let x_square = Point::from_ab("B2").unwrap();
+    assert!(eval::tile_weight(&board, corner) > 0);
+    assert!(eval::tile_weight(&board, x_square) < 0);
+
+    let s = "B_______
+             ________
+             ________
+             ___WB___
+             ___BW___
+             ________
+             ________
+             ________";
+    let board = Position::parse(s).unwrap().board;
+    let black = eval::sev3(&board, Cell::Black, false);
+    let white = eval::sev3(&board, Cell::White, false);
+    assert!(black > white);
+}
+
+#[test]
+fn games_end_with_a_result() {
+    let mut game = Reversi {
+        position: Position::initial(4, &[]).unwrap(),
+        variant: Variant::Anti,
+    };
+    while let Some(player_move) = game.legal_moves().first() {
+        game.play(&player_move.clone());
+    }
+    assert!(game.result().is_over());
+}
+
+#[test]
+fn every_engine_searches() {
+    let position = Position::initial(6, &[]).unwrap();
+    let legal = position
+        .allowed_moves()
+        .iter()
+        .map(|m| m.0)
+        .collect::<Vec<_>>();
+    for &engine in Engine::ALL.iter() {
+        for &variant in [Variant::Normal, Variant::Anti].iter() {
+            let report =
+                engine.search(position, variant, playouts(200)).unwrap();
+            let best = report.best_move().expect("a move");
+            assert!(legal.contains(&best), "{:?} played {:?}", engine, best);
+            assert_eq!(report.color, Cell::Black);
+        }
+    }
+}
+
+#[test]
+fn time_limits_and_options() {
+    let position = Position::initial(6, &[]).unwrap();
+    let limits = Limits {
+        time: Some(Duration::from_millis(50)),
+        playouts: None,
+    };
+    let mut config = BotConfig::new(Engine::MctsRave, Variant::Anti, limits);
+    config.rave_k = 50.0;
+    let report = config.search(position).unwrap();
+    assert!(report.nodes > 0);
+    assert!(report.best_move().is_some());
+
+    // Minimax deepens until the time is up
+    config.engine = Engine::Minimax;
+    config.max_depth = 20;
+    let report = config.search(position).unwrap();
+    assert!(report.depth > 1 && report.depth < 21);
+
+    // A search without limits would never end
+    let endless = Limits {
+        time: None,
+        playouts: None,
+    };
+    assert!(Engine::Mcts
+        .search(position, Variant::Anti, endless)
+        .is_err());
+}
+
+#[test]
+fn passing_position() {
+    let s = "WWWW
+             WWWW
+             BBWW
+             B___";
+    let position = Position::parse(s).unwrap();
+    let report = Engine::Mcts
+        .search(position, Variant::Normal, playouts(10))
+        .unwrap();
+    assert_eq!(report.best_move(), None);
+    let game = Reversi {
+        position,
+        variant: Variant::Normal,
+    };
+    assert_eq!(game.result(), EndState::Unknown);
+}
@@ -0,0 +1,42 @@
+use std::{
+    env, fs,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Everything the binary printed for the commands in `input`
+fn session(args: &[&str], input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_reversi_bot"))
+        .arg("session")
+        .args(args)
+        .args(["--bot", "--bot-impl minimax --max-depth 2"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(input.as_bytes()).unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn game_over_with_the_bot_to_move() {
+    // Black's only move is C4, white's A2 fills the board
+    let path = env::temp_dir().join("reversi_bot_session_end.txt");
+    fs::write(&path, "WBWB\n_BBW\nWWWW\nWB_B\nblack\n").unwrap();
+    let path = path.to_str().unwrap();
+    let args = ["--board-size", "4", "--position", path];
+    let input = "newgame black\nA2\nnewgame black\nA2\n";
+    assert_eq!(session(&args, input), "C4\nC4\n");
+}
+
+#[test]
+fn bad_games_skipped() {
+    // A hole on a starting disc, then a line that isn't a command
+    let input = "newgame black B2\nhello\nnewgame white A1\n";
+    assert_eq!(session(&["--board-size", "4"], input), "");
+}